@@ -0,0 +1,14 @@
+use openweather::{AsyncClient, LocationSpecifier};
+static API_KEY: &str = "YOUR_API_KEY_HERE";
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<(), openweather::Error> {
+    let client = AsyncClient::new(API_KEY)?;
+    let loc = LocationSpecifier::CityAndCountryName {
+        city: "Minneapolis".to_string(),
+        country: "USA".to_string(),
+    };
+    let weather = client.get_current_weather(&loc).await?;
+    println!("Right now in Minneapolis, MN it is {}K", weather.main.temp);
+    Ok(())
+}
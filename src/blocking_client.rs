@@ -0,0 +1,398 @@
+use std::sync::Arc;
+use std::thread;
+
+use log::debug;
+use time::{Date, UtcOffset};
+
+use crate::client::{ClientBuilder, Config};
+use crate::pipeline::{self, Attempts, Next, Stored};
+use crate::request::{self, Request, Resolve};
+use crate::weather_types::*;
+use crate::{
+    CacheStats, HttpReqTransport, HttpResponse, KeyStats, LocationSpecifier, OneCall,
+    RateLimitStats, Result, Settings, Timestamp, Transport,
+};
+
+/// A reusable handle to the OpenWeatherMap API.
+///
+/// The client owns the API key, the default `Settings` applied to every
+/// request and the transport configuration, so it can be built once and
+/// shared instead of threading the key through every call.
+///
+/// ```no_run
+/// use openweather::{Client, LocationSpecifier, Settings, Unit};
+///
+/// let client = Client::new("YOUR_API_KEY_HERE");
+/// let loc = LocationSpecifier::CityId("5037649".to_string());
+/// let weather = client.get_current_weather(&loc)?;
+///
+/// // Override the unit for a single call
+/// let imperial = client.with_settings(&Settings {
+///     unit: Some(Unit::Imperial),
+///     lang: None,
+/// });
+/// let weather = imperial.get_current_weather(&loc)?;
+/// # Ok::<(), openweather::Error>(())
+/// ```
+///
+/// Requests go through a `Transport`, `HttpReqTransport` unless another one
+/// is given to `ClientBuilder::build_with_transport`.
+#[derive(Debug)]
+pub struct Client<T = HttpReqTransport> {
+    config: Config,
+    transport: Arc<T>,
+}
+
+impl<T> Clone for Client<T> {
+    fn clone(&self) -> Self {
+        Client {
+            config: self.config.clone(),
+            transport: self.transport.clone(),
+        }
+    }
+}
+
+impl Client {
+    /// Creates a client with default settings and timeouts.
+    pub fn new(key: &str) -> Client {
+        Client::builder(key).build()
+    }
+
+    pub fn builder(key: &str) -> ClientBuilder {
+        ClientBuilder::new(key)
+    }
+}
+
+impl<T: Transport> Client<T> {
+    pub(crate) fn from_parts(config: Config, transport: T) -> Client<T> {
+        Client {
+            config,
+            transport: Arc::new(transport),
+        }
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// The default settings applied to every request.
+    pub fn settings(&self) -> &Settings {
+        self.config.settings()
+    }
+
+    /// Returns a copy of this client where every setting given in `overrides`
+    /// replaces the client's default, e.g. to request a single call in a
+    /// different unit or language. The transport is shared with the original.
+    pub fn with_settings(&self, overrides: &Settings) -> Client<T> {
+        Client {
+            config: self.config.with_settings(overrides),
+            transport: self.transport.clone(),
+        }
+    }
+
+    /// Counters of every key the client sends requests with.
+    pub fn key_stats(&self) -> Vec<KeyStats> {
+        self.config.keys().stats()
+    }
+
+    /// Counters of the client's `RateLimit`, `None` when it has none.
+    pub fn rate_limit_stats(&self) -> Option<RateLimitStats> {
+        self.config.rate_limiter().map(|limiter| limiter.stats())
+    }
+
+    /// Sends a single GET once the rate limit allows, sharing it with
+    /// concurrent identical requests when coalescing.
+    fn get<R>(&self, attempts: &mut Attempts<'_, R>, key: &str) -> Result<HttpResponse>
+    where
+        R: serde::de::DeserializeOwned + Report,
+    {
+        let mut send = || {
+            self.wait_for_rate_limit()?;
+            self.transport.get(&attempts.url()?)
+        };
+        match self.config.coalescer() {
+            Some(coalescer) => coalescer.run(key, send),
+            None => send(),
+        }
+    }
+
+    fn wait_for_rate_limit(&self) -> Result<()> {
+        if let Some(limiter) = self.config.rate_limiter() {
+            let wait = limiter.acquire()?;
+            if !wait.is_zero() {
+                debug!("Rate limit reached, waiting {:?}", wait);
+                thread::sleep(wait);
+            }
+        }
+        Ok(())
+    }
+
+    /// Counters of the client's `Cache`, `None` when it has none.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.config.cache().map(|cache| cache.stats())
+    }
+
+    /// Drops every cached response.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = self.config.cache() {
+            cache.clear();
+        }
+    }
+
+    fn send<R>(&self, request: Request<R>) -> Result<R>
+    where
+        R: serde::de::DeserializeOwned + Report + 'static,
+    {
+        let key = self.config.cache_key(&request)?;
+        if let Some(report) = pipeline::cached(&self.config, &key) {
+            return report;
+        }
+
+        let stored = self
+            .config
+            .disk_cache()
+            .and_then(|cache| cache.load(&key, request.endpoint()));
+        let stored = match Stored::new(&self.config, &key, stored) {
+            Stored::Use(res) => return request::parse(&res, self.config.unit()),
+            Stored::Revalidate(res, revalidation) => {
+                if let Some(revalidation) = revalidation {
+                    let client = self.clone();
+                    thread::spawn(move || {
+                        let _revalidation = revalidation;
+                        if let Err(err) = client.fetch(&request, &key) {
+                            debug!("Revalidating {} failed: {}", key, err);
+                        }
+                    });
+                }
+                return request::parse(&res, self.config.unit());
+            }
+            Stored::FallBack(stored) => stored,
+        };
+        let result = self.fetch(&request, &key);
+        pipeline::fall_back(&self.config, result, stored)
+    }
+
+    /// Sends a request, see `Attempts`, and caches the response when it
+    /// holds the report.
+    fn fetch<R>(&self, request: &Request<R>, key: &str) -> Result<R>
+    where
+        R: serde::de::DeserializeOwned + Report,
+    {
+        let mut attempts = Attempts::new(&self.config, request);
+        loop {
+            let result = self.get(&mut attempts, key);
+            match attempts.next(result) {
+                Next::Report(report, res) => {
+                    if let Some(cache) = self.config.disk_cache() {
+                        cache.store(key, &res);
+                    }
+                    pipeline::store(&self.config, request, key, res);
+                    return Ok(report);
+                }
+                Next::Failed(err) => return Err(err),
+                Next::Retry(delay) => thread::sleep(delay),
+            }
+        }
+    }
+
+    pub fn get_current_weather(
+        &self,
+        location: &LocationSpecifier,
+    ) -> Result<WeatherReportCurrent> {
+        self.send(request::current_weather(location)?)
+    }
+
+    /// Current weather for every city of a multi-city `LocationSpecifier`:
+    /// up to 20 `CityIds`, up to 50 cities in a `Circle` or the cities in a
+    /// `BoundingBox`. Single-city specifiers are rejected with `Error::Input`.
+    pub fn get_current_weather_cities(
+        &self,
+        location: &LocationSpecifier,
+    ) -> Result<WeatherReportCities> {
+        self.send(request::cities_current_weather(location)?)
+    }
+
+    pub fn get_5_day_forecast(&self, location: &LocationSpecifier) -> Result<WeatherReport5Day> {
+        self.send(request::forecast_5_day(location)?)
+    }
+
+    pub fn get_16_day_forecast(
+        &self,
+        location: &LocationSpecifier,
+        len: u8,
+    ) -> Result<WeatherReport16Day> {
+        self.send(request::forecast_16_day(location, len)?)
+    }
+
+    pub fn get_one_call_current(&self, coordinates: &Coordinates) -> Result<WeatherReportOneCall> {
+        self.send(request::one_call_current(coordinates))
+    }
+
+    /// Sends a One Call API 3.0 request, which needs its own subscription.
+    pub fn get_one_call(&self, request: &OneCall) -> Result<WeatherReportOneCall3> {
+        self.send(request::one_call(request))
+    }
+
+    /// Weather aggregated over the calendar day of `date`, available from
+    /// 1979-01-02 up to 1.5 years ahead. The day runs from midnight to
+    /// midnight at `utc_offset`, the timezone of `coordinates` when `None`.
+    pub fn get_one_call_day_summary(
+        &self,
+        coordinates: &Coordinates,
+        date: Date,
+        utc_offset: Option<UtcOffset>,
+    ) -> Result<WeatherReportDaySummary> {
+        self.send(request::one_call_day_summary(
+            coordinates,
+            date,
+            utc_offset,
+        )?)
+    }
+
+    /// A human-readable summary of the weather for today or tomorrow, today
+    /// when `date` is `None`.
+    pub fn get_one_call_overview(
+        &self,
+        coordinates: &Coordinates,
+        date: Option<Date>,
+    ) -> Result<WeatherReportOverview> {
+        self.send(request::one_call_overview(coordinates, date))
+    }
+
+    pub fn get_one_call_historical(
+        &self,
+        coordinates: &Coordinates,
+        dt: impl Into<Timestamp>,
+    ) -> Result<WeatherReportOneCallHistorical> {
+        self.send(request::one_call_historical(coordinates, dt.into()))
+    }
+
+    pub fn get_historical_data(
+        &self,
+        location: &LocationSpecifier,
+        start: impl Into<Timestamp>,
+        end: impl Into<Timestamp>,
+    ) -> Result<WeatherReportHistorical> {
+        self.send(request::historical_data(
+            location,
+            start.into(),
+            end.into(),
+        )?)
+    }
+
+    pub fn get_accumulated_temperature_data(
+        &self,
+        location: &LocationSpecifier,
+        start: impl Into<Timestamp>,
+        end: impl Into<Timestamp>,
+        threshold: u32,
+    ) -> Result<WeatherAccumulatedTemperature> {
+        self.send(request::accumulated_temperature(
+            location,
+            start.into(),
+            end.into(),
+            threshold,
+        )?)
+    }
+
+    pub fn get_accumulated_precipitation_data(
+        &self,
+        location: &LocationSpecifier,
+        start: impl Into<Timestamp>,
+        end: impl Into<Timestamp>,
+        threshold: u32,
+    ) -> Result<WeatherAccumulatedPrecipitation> {
+        self.send(request::accumulated_precipitation(
+            location,
+            start.into(),
+            end.into(),
+            threshold,
+        )?)
+    }
+
+    pub fn get_current_uv_index(&self, location: &LocationSpecifier) -> Result<UvIndex> {
+        self.send(request::current_uv_index(location)?)
+    }
+
+    pub fn get_forecast_uv_index(
+        &self,
+        location: &LocationSpecifier,
+        len: u8,
+    ) -> Result<ForecastUvIndex> {
+        self.send(request::forecast_uv_index(location, len)?)
+    }
+
+    pub fn get_historical_uv_index(
+        &self,
+        location: &LocationSpecifier,
+        start: impl Into<Timestamp>,
+        end: impl Into<Timestamp>,
+    ) -> Result<HistoricalUvIndex> {
+        self.send(request::historical_uv_index(
+            location,
+            start.into(),
+            end.into(),
+        )?)
+    }
+
+    /// Current air quality and pollutant concentrations.
+    pub fn get_current_air_pollution(&self, coordinates: &Coordinates) -> Result<AirPollution> {
+        self.send(request::current_air_pollution(coordinates))
+    }
+
+    /// Hourly air quality forecast for the next 4 days.
+    pub fn get_forecast_air_pollution(&self, coordinates: &Coordinates) -> Result<AirPollution> {
+        self.send(request::forecast_air_pollution(coordinates))
+    }
+
+    /// Hourly air quality between `start` and `end`, available from
+    /// November 27th 2020.
+    pub fn get_historical_air_pollution(
+        &self,
+        coordinates: &Coordinates,
+        start: impl Into<Timestamp>,
+        end: impl Into<Timestamp>,
+    ) -> Result<AirPollution> {
+        self.send(request::historical_air_pollution(
+            coordinates,
+            start.into(),
+            end.into(),
+        ))
+    }
+
+    /// Looks up places by name, `query` being `"{city},{state},{country}"`
+    /// with state and country optional. Returns up to `limit` (1 to 5)
+    /// matches.
+    pub fn get_geocoding_direct(&self, query: &str, limit: u8) -> Result<Vec<GeocodingLocation>> {
+        self.send(request::geocoding_direct(query, limit)?)
+    }
+
+    /// Looks up the names of places near `coordinates`, returning up to
+    /// `limit` (1 to 5) matches.
+    pub fn get_geocoding_reverse(
+        &self,
+        coordinates: &Coordinates,
+        limit: u8,
+    ) -> Result<Vec<GeocodingLocation>> {
+        self.send(request::geocoding_reverse(coordinates, limit)?)
+    }
+
+    /// Looks up a zip or post code, `country` being an ISO 3166 code or empty
+    /// for the US.
+    pub fn get_geocoding_zip(&self, zip: &str, country: &str) -> Result<GeocodingZip> {
+        self.send(request::geocoding_zip(zip, country))
+    }
+
+    /// Finds the coordinates of a single-city `LocationSpecifier`, e.g. for
+    /// `get_one_call_current`. Names and zip codes are geocoded, city IDs
+    /// cost a current weather request; multi-city specifiers are rejected
+    /// with `Error::Input`.
+    pub fn resolve_coordinates(&self, location: &LocationSpecifier) -> Result<Coordinates> {
+        match request::resolve_coordinates(location)? {
+            Resolve::Known(coordinates) => Ok(coordinates),
+            Resolve::Direct(request) => request::first_match(location, self.send(request)?),
+            Resolve::Zip(request) => Ok(self.send(request)?.coord),
+            Resolve::Weather(request) => Ok(self.send(request)?.coord),
+        }
+    }
+}
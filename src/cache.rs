@@ -0,0 +1,284 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use url::Url;
+
+use crate::HttpResponse;
+
+/// The kinds of endpoints a `Cache` keeps responses of for different times,
+/// following how often OpenWeatherMap updates the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    /// Current weather, UV index and air pollution, and the One Call
+    /// reports, updated about every 10 minutes
+    Current,
+    /// Forecasts, updated a few times a day
+    Forecast,
+    /// Historical data, which does not change
+    Historical,
+    /// Geocoding
+    Geocoding,
+}
+
+/// An in-memory cache of successful responses, see `ClientBuilder::cache`.
+///
+/// Responses are kept per endpoint kind for its time to live, 10 minutes
+/// for current data, 1 hour for forecasts and a day for historical data and
+/// geocoding by default; a zero time to live disables caching the kind.
+/// Once `max_entries` are stored, the least recently used entry makes room
+/// for a new one. Entries are keyed on the request URL without the API key,
+/// so clients sharing a cache but not a key share their entries.
+///
+/// ```
+/// use std::time::Duration;
+/// use openweather::{Cache, Endpoint};
+///
+/// let cache = Cache::new(500).ttl(Endpoint::Forecast, Duration::from_secs(3 * 60 * 60));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cache {
+    max_entries: usize,
+    ttls: HashMap<Endpoint, Duration>,
+}
+
+impl Cache {
+    pub fn new(max_entries: usize) -> Cache {
+        Cache {
+            max_entries,
+            ttls: default_ttls(),
+        }
+    }
+
+    /// How long responses of `endpoint` are kept.
+    pub fn ttl(mut self, endpoint: Endpoint, ttl: Duration) -> Self {
+        self.ttls.insert(endpoint, ttl);
+        self
+    }
+
+    pub(crate) fn ttl_of(&self, endpoint: Endpoint) -> Duration {
+        self.ttls.get(&endpoint).copied().unwrap_or_default()
+    }
+}
+
+/// The times to live of a `Cache` and a `DiskCache` unless set otherwise.
+pub(crate) fn default_ttls() -> HashMap<Endpoint, Duration> {
+    vec![
+        (Endpoint::Current, Duration::from_secs(10 * 60)),
+        (Endpoint::Forecast, Duration::from_secs(60 * 60)),
+        (Endpoint::Historical, Duration::from_secs(24 * 60 * 60)),
+        (Endpoint::Geocoding, Duration::from_secs(24 * 60 * 60)),
+    ]
+    .into_iter()
+    .collect()
+}
+
+/// Counters of a client's `Cache`, see `Client::cache_stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Requests answered from the cache
+    pub hits: u64,
+    /// Requests sent because no fresh entry was cached
+    pub misses: u64,
+    /// Entries dropped to make room for newer ones
+    pub evictions: u64,
+    /// Entries currently stored, including expired ones not yet dropped
+    pub entries: usize,
+}
+
+/// The key of a request in a cache: its URL without the API key, with the
+/// query parameters sorted so their order does not matter.
+pub(crate) fn cache_key(url: &Url) -> String {
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .into_owned()
+        .filter(|(name, _)| !name.eq_ignore_ascii_case("appid"))
+        .collect();
+    params.sort();
+
+    let mut key = url.clone();
+    key.set_fragment(None);
+    key.query_pairs_mut().clear().extend_pairs(params);
+    key.into()
+}
+
+#[derive(Debug)]
+struct Entry {
+    response: HttpResponse,
+    expires: Instant,
+    used: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: HashMap<String, Entry>,
+    /// Keys by the tick they were last used at, oldest first
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl State {
+    fn touch(&mut self, key: &str) {
+        self.tick += 1;
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.used);
+            entry.used = self.tick;
+            self.recency.insert(self.tick, key.to_string());
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.used);
+        }
+    }
+}
+
+/// The shared state of a client's `Cache`.
+#[derive(Debug)]
+pub(crate) struct MemoryCache {
+    cache: Cache,
+    state: Mutex<State>,
+}
+
+impl MemoryCache {
+    pub fn new(cache: Cache) -> MemoryCache {
+        MemoryCache {
+            cache,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// The cached response for `key` if it has not expired yet.
+    pub fn get(&self, key: &str) -> Option<HttpResponse> {
+        self.get_at(key, Instant::now())
+    }
+
+    fn get_at(&self, key: &str, now: Instant) -> Option<HttpResponse> {
+        let mut state = self.state.lock().unwrap();
+        match state.entries.get(key) {
+            Some(entry) if entry.expires > now => {
+                let response = entry.response.clone();
+                state.touch(key);
+                state.stats.hits += 1;
+                Some(response)
+            }
+            expired => {
+                if expired.is_some() {
+                    state.remove(key);
+                }
+                state.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Stores a response of `endpoint` under `key`.
+    pub fn insert(&self, key: String, endpoint: Endpoint, response: HttpResponse) {
+        self.insert_at(key, endpoint, response, Instant::now())
+    }
+
+    fn insert_at(&self, key: String, endpoint: Endpoint, response: HttpResponse, now: Instant) {
+        let ttl = self.cache.ttl_of(endpoint);
+        if ttl.is_zero() || self.cache.max_entries == 0 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        state.remove(&key);
+        while state.entries.len() >= self.cache.max_entries {
+            let oldest = match state.recency.iter().next() {
+                Some((_, key)) => key.clone(),
+                None => break,
+            };
+            state.remove(&oldest);
+            state.stats.evictions += 1;
+        }
+        state.entries.insert(
+            key.clone(),
+            Entry {
+                response,
+                expires: now + ttl,
+                used: 0,
+            },
+        );
+        state.touch(&key);
+    }
+
+    pub fn clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.entries.clear();
+        state.recency.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let state = self.state.lock().unwrap();
+        CacheStats {
+            entries: state.entries.len(),
+            ..state.stats
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use url::Url;
+
+    use super::{cache_key, MemoryCache};
+    use crate::{Cache, Endpoint, HttpResponse};
+
+    fn response(body: &str) -> HttpResponse {
+        HttpResponse {
+            status: 200,
+            headers: vec![],
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn key_ignores_the_api_key_and_parameter_order() {
+        let a = Url::parse("https://h/data/2.5/weather?id=1&APPID=a&units=metric").unwrap();
+        let b = Url::parse("https://h/data/2.5/weather?units=metric&id=1&APPID=b").unwrap();
+        assert_eq!(cache_key(&a), cache_key(&b));
+        assert_eq!(
+            cache_key(&a),
+            "https://h/data/2.5/weather?id=1&units=metric"
+        );
+    }
+
+    #[test]
+    fn entries_expire_per_endpoint() {
+        let cache = MemoryCache::new(Cache::new(10).ttl(Endpoint::Geocoding, Duration::ZERO));
+        let start = Instant::now();
+        cache.insert_at("now".to_string(), Endpoint::Current, response("1"), start);
+        cache.insert_at("geo".to_string(), Endpoint::Geocoding, response("2"), start);
+
+        assert!(cache
+            .get_at("now", start + Duration::from_secs(599))
+            .is_some());
+        assert!(cache
+            .get_at("now", start + Duration::from_secs(600))
+            .is_none());
+        assert!(cache.get_at("geo", start).is_none());
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 0));
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_entry() {
+        let cache = MemoryCache::new(Cache::new(2));
+        let start = Instant::now();
+        for key in &["a", "b"] {
+            cache.insert_at(key.to_string(), Endpoint::Forecast, response(key), start);
+        }
+        assert!(cache.get_at("a", start).is_some());
+        cache.insert_at("c".to_string(), Endpoint::Forecast, response("c"), start);
+
+        assert!(cache.get_at("b", start).is_none());
+        assert!(cache.get_at("a", start).is_some());
+        assert!(cache.get_at("c", start).is_some());
+        assert_eq!(cache.stats().evictions, 1);
+    }
+}
@@ -0,0 +1,377 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use flate2::bufread::GzDecoder;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{Coordinates, LocationSpecifier};
+
+/// The mean radius of the earth in kilometers.
+const EARTH_RADIUS: f64 = 6371.0;
+
+/// A city of OpenWeatherMap's city list, see `CityList`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct City {
+    pub id: u32,
+    pub name: String,
+    /// The state of US cities, empty elsewhere
+    #[serde(default)]
+    pub state: String,
+    pub country: String,
+    pub coord: Coordinates,
+}
+
+impl City {
+    /// The location to request the weather of the city with.
+    pub fn location(&self) -> LocationSpecifier {
+        LocationSpecifier::CityId(self.id.to_string())
+    }
+
+    /// The great-circle distance to a point in kilometers.
+    pub fn distance(&self, lat: f32, lon: f32) -> f64 {
+        let (lat1, lon1) = (
+            f64::from(self.coord.lat).to_radians(),
+            f64::from(self.coord.lon).to_radians(),
+        );
+        let (lat2, lon2) = (f64::from(lat).to_radians(), f64::from(lon).to_radians());
+        let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+            + lat1.cos() * lat2.cos() * ((lon2 - lon1) / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+    }
+}
+
+/// OpenWeatherMap's list of cities, `city.list.json` or `city.list.json.gz`
+/// from <https://bulk.openweathermap.org/sample/>, indexed to find the ID of
+/// a city without a call to the API. Needs the `city-list` feature.
+///
+/// ```no_run
+/// use openweather::CityList;
+///
+/// let cities = CityList::load("city.list.json.gz")?;
+/// let minneapolis = cities.search("Mineapolis", Some("US"), 1);
+/// let closest = cities.nearest(44.98, -93.26, 5);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct CityList {
+    /// Sorted by ID
+    cities: Vec<City>,
+    /// The lower case names of `cities`
+    names: Vec<String>,
+    by_name: HashMap<String, Vec<usize>>,
+    /// Indices of `cities` laid out as a k-d tree over `points`, each node
+    /// the median of its range
+    tree: Vec<usize>,
+    /// The cities as points on the unit sphere, so nearness is the same
+    /// across the antimeridian and near the poles
+    points: Vec<[f64; 3]>,
+}
+
+impl CityList {
+    pub fn new(mut cities: Vec<City>) -> CityList {
+        cities.sort_by_key(|city| city.id);
+        let names: Vec<String> = cities.iter().map(|city| normalize(&city.name)).collect();
+        let mut by_name: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, name) in names.iter().enumerate() {
+            by_name.entry(name.clone()).or_default().push(index);
+        }
+        let points: Vec<[f64; 3]> = cities
+            .iter()
+            .map(|city| point(city.coord.lat, city.coord.lon))
+            .collect();
+        let mut tree: Vec<usize> = (0..cities.len()).collect();
+        build(&mut tree, &points, 0);
+        CityList {
+            cities,
+            names,
+            by_name,
+            tree,
+            points,
+        }
+    }
+
+    /// Reads the list from a file, gzip compressed or not.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<CityList> {
+        CityList::from_reader(File::open(path)?)
+    }
+
+    /// Reads the list as JSON, gzip compressed or not.
+    pub fn from_reader(reader: impl Read) -> io::Result<CityList> {
+        let mut reader = BufReader::new(reader);
+        let cities: Vec<City> = if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+            serde_json::from_reader(BufReader::new(GzDecoder::new(reader)))?
+        } else {
+            serde_json::from_reader(reader)?
+        };
+        Ok(CityList::new(cities))
+    }
+
+    pub fn len(&self) -> usize {
+        self.cities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cities.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &City> {
+        self.cities.iter()
+    }
+
+    pub fn get(&self, id: u32) -> Option<&City> {
+        self.cities
+            .binary_search_by_key(&id, |city| city.id)
+            .ok()
+            .map(|index| &self.cities[index])
+    }
+
+    /// The cities named `name`, ignoring case, in `country` when given.
+    pub fn find(&self, name: &str, country: Option<&str>) -> Vec<&City> {
+        self.by_name
+            .get(&normalize(name))
+            .into_iter()
+            .flatten()
+            .map(|&index| &self.cities[index])
+            .filter(|city| in_country(city, country))
+            .collect()
+    }
+
+    /// Up to `limit` cities with a name like `query`, in `country` when
+    /// given, best matches first: exact names, then names starting with
+    /// `query`, then names within an edit distance of a third of its length
+    /// for typos.
+    pub fn search(&self, query: &str, country: Option<&str>, limit: usize) -> Vec<&City> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return vec![];
+        }
+        let query_chars: Vec<char> = query.chars().collect();
+        let max_distance = query_chars.len() / 3;
+
+        let mut matches: Vec<(usize, usize, usize)> = self
+            .names
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| in_country(&self.cities[index], country))
+            .filter_map(|(index, name)| {
+                let rank = if *name == query {
+                    (0, 0)
+                } else if name.starts_with(&query) {
+                    (1, name.chars().count() - query_chars.len())
+                } else {
+                    (2, edit_distance(&query_chars, name, max_distance)?)
+                };
+                Some((rank.0, rank.1, index))
+            })
+            .collect();
+        matches.sort_unstable();
+        matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, _, index)| &self.cities[index])
+            .collect()
+    }
+
+    /// The `count` cities closest to a point, closest first.
+    pub fn nearest(&self, lat: f32, lon: f32, count: usize) -> Vec<&City> {
+        let mut nearest = Nearest {
+            target: point(lat, lon),
+            count,
+            found: Vec::with_capacity(count.min(self.len()).saturating_add(1)),
+        };
+        if count > 0 {
+            nearest.search(&self.tree, &self.points, 0);
+        }
+        nearest
+            .found
+            .into_iter()
+            .map(|(_, index)| &self.cities[index])
+            .collect()
+    }
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+fn in_country(city: &City, country: Option<&str>) -> bool {
+    country.is_none_or(|country| city.country.eq_ignore_ascii_case(country.trim()))
+}
+
+/// The Levenshtein distance between `query` and `name`, `None` when it is
+/// over `max`.
+fn edit_distance(query: &[char], name: &str, max: usize) -> Option<usize> {
+    let name: Vec<char> = name.chars().collect();
+    if name.len().abs_diff(query.len()) > max {
+        return None;
+    }
+    let mut previous: Vec<usize> = (0..=name.len()).collect();
+    let mut current = vec![0; name.len() + 1];
+    for (i, q) in query.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, n) in name.iter().enumerate() {
+            let substitution = previous[j] + usize::from(q != n);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().min().is_some_and(|&min| min > max) {
+            return None;
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    Some(previous[name.len()]).filter(|&distance| distance <= max)
+}
+
+fn point(lat: f32, lon: f32) -> [f64; 3] {
+    let (lat, lon) = (f64::from(lat).to_radians(), f64::from(lon).to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn squared_distance(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b).powi(2)).sum()
+}
+
+/// Orders `nodes` into a k-d tree, splitting on the axes in turn.
+fn build(nodes: &mut [usize], points: &[[f64; 3]], depth: usize) {
+    if nodes.len() <= 1 {
+        return;
+    }
+    let axis = depth % 3;
+    let mid = nodes.len() / 2;
+    nodes.select_nth_unstable_by(mid, |&a, &b| {
+        points[a][axis]
+            .partial_cmp(&points[b][axis])
+            .unwrap_or(Ordering::Equal)
+    });
+    let (left, right) = nodes.split_at_mut(mid);
+    build(left, points, depth + 1);
+    build(&mut right[1..], points, depth + 1);
+}
+
+/// A nearest neighbours search through a tree laid out by `build`.
+struct Nearest {
+    target: [f64; 3],
+    count: usize,
+    /// The closest points so far by squared distance, closest first
+    found: Vec<(f64, usize)>,
+}
+
+impl Nearest {
+    fn search(&mut self, nodes: &[usize], points: &[[f64; 3]], depth: usize) {
+        if nodes.is_empty() {
+            return;
+        }
+        let axis = depth % 3;
+        let mid = nodes.len() / 2;
+        let index = nodes[mid];
+        self.offer(squared_distance(&points[index], &self.target), index);
+
+        let offset = self.target[axis] - points[index][axis];
+        let (near, far) = if offset < 0.0 {
+            (&nodes[..mid], &nodes[mid + 1..])
+        } else {
+            (&nodes[mid + 1..], &nodes[..mid])
+        };
+        self.search(near, points, depth + 1);
+        if self.found.len() < self.count || offset * offset < self.found[self.found.len() - 1].0 {
+            self.search(far, points, depth + 1);
+        }
+    }
+
+    fn offer(&mut self, distance: f64, index: usize) {
+        let at = self.found.partition_point(|&(found, _)| found <= distance);
+        if at < self.count {
+            self.found.insert(at, (distance, index));
+            self.found.truncate(self.count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::CityList;
+
+    const CITIES: &str = r#"[
+        {"id": 5037649, "name": "Minneapolis", "state": "MN", "country": "US", "coord": {"lon": -93.26384, "lat": 44.979969}},
+        {"id": 5045360, "name": "Saint Paul", "state": "MN", "country": "US", "coord": {"lon": -93.093269, "lat": 44.944408}},
+        {"id": 4275586, "name": "Minneapolis", "state": "KS", "country": "US", "coord": {"lon": -97.706711, "lat": 39.121948}},
+        {"id": 2643743, "name": "London", "state": "", "country": "GB", "coord": {"lon": -0.12574, "lat": 51.50853}},
+        {"id": 6058560, "name": "London", "state": "", "country": "CA", "coord": {"lon": -81.23304, "lat": 42.983391}},
+        {"id": 2193733, "name": "Auckland", "state": "", "country": "NZ", "coord": {"lon": 174.766663, "lat": -36.866669}},
+        {"id": 4032243, "name": "Apia", "state": "", "country": "WS", "coord": {"lon": -171.766663, "lat": -13.83333}}
+    ]"#;
+
+    fn cities() -> CityList {
+        CityList::from_reader(CITIES.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn looks_up_cities_by_id_and_name() {
+        let cities = cities();
+        assert_eq!(cities.len(), 7);
+        assert_eq!(cities.get(2643743).unwrap().name, "London");
+        assert!(cities.get(1).is_none());
+
+        assert_eq!(cities.find("london", None).len(), 2);
+        let london = cities.find(" LONDON ", Some("ca"));
+        assert_eq!(london.len(), 1);
+        assert_eq!(london[0].location().to_string(), "id:6058560");
+    }
+
+    #[test]
+    fn search_ranks_exact_then_prefix_then_typos() {
+        let cities = cities();
+        let ids = |query: &str, country: Option<&str>| -> Vec<u32> {
+            cities
+                .search(query, country, 10)
+                .iter()
+                .map(|city| city.id)
+                .collect()
+        };
+        assert_eq!(ids("Mineapolis", Some("US")), vec![4275586, 5037649]);
+        assert_eq!(ids("saint", None), vec![5045360]);
+        assert_eq!(ids("Lodnon", Some("GB")), vec![2643743]);
+        assert!(ids("Paris", None).is_empty());
+    }
+
+    #[test]
+    fn finds_the_nearest_cities() {
+        let cities = cities();
+        let ids = |lat: f32, lon: f32, count: usize| -> Vec<u32> {
+            cities
+                .nearest(lat, lon, count)
+                .iter()
+                .map(|city| city.id)
+                .collect()
+        };
+        assert_eq!(ids(44.98, -93.2, 2), vec![5037649, 5045360]);
+        assert_eq!(ids(51.0, 0.0, 1), vec![2643743]);
+        // Closer across the antimeridian than by longitude
+        assert_eq!(ids(-20.0, 179.0, 1), vec![4032243]);
+        assert!(ids(0.0, 0.0, 0).is_empty());
+        assert_eq!(ids(0.0, 0.0, 100).len(), 7);
+        assert_eq!(ids(0.0, 0.0, usize::MAX).len(), 7);
+
+        let minneapolis = cities.get(5037649).unwrap();
+        let distance = minneapolis.distance(44.9444, -93.0933);
+        assert!((distance - 14.0).abs() < 0.5, "{}", distance);
+    }
+
+    #[test]
+    fn reads_gzip_compressed_lists() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(CITIES.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        let cities = CityList::from_reader(compressed.as_slice()).unwrap();
+        assert_eq!(cities.len(), 7);
+        assert!(CityList::from_reader(&b"{}"[..]).is_err());
+    }
+}
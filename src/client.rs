@@ -0,0 +1,332 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use url::Url;
+
+use crate::cache::{cache_key, MemoryCache};
+use crate::coalesce::Coalescer;
+use crate::disk_cache::Revalidations;
+use crate::keys::Keys;
+use crate::rate_limit::RateLimiter;
+use crate::request::{Api, Request};
+use crate::{Cache, DiskCache, KeyPool, RateLimit, Result, RetryPolicy, Settings, Unit};
+
+#[cfg(feature = "async")]
+use crate::{AsyncClient, AsyncTransport, ReqwestTransport};
+#[cfg(feature = "blocking")]
+use crate::{Client, HttpReqTransport, Transport};
+
+static API_BASE: &str = "https://api.openweathermap.org/";
+static API_VERSION: &str = "2.5";
+static ONE_CALL_API_VERSION: &str = "3.0";
+static GEO_API_VERSION: &str = "1.0";
+
+/// Everything a client needs to turn a `Request` into a URL, shared between
+/// the blocking and the async client.
+#[derive(Debug, Clone)]
+pub(crate) struct Config {
+    keys: Arc<Keys>,
+    settings: Settings,
+    base_url: Url,
+    api_version: String,
+    retry: RetryPolicy,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    cache: Option<Arc<MemoryCache>>,
+    disk_cache: Option<Arc<DiskCache>>,
+    revalidations: Arc<Revalidations>,
+    coalescer: Option<Arc<Coalescer>>,
+}
+
+impl Config {
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn retry(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> {
+        self.rate_limiter.as_deref()
+    }
+
+    pub fn cache(&self) -> Option<&MemoryCache> {
+        self.cache.as_deref()
+    }
+
+    pub fn disk_cache(&self) -> Option<&Arc<DiskCache>> {
+        self.disk_cache.as_ref()
+    }
+
+    pub fn revalidations(&self) -> &Arc<Revalidations> {
+        &self.revalidations
+    }
+
+    pub fn coalescer(&self) -> Option<&Coalescer> {
+        self.coalescer.as_deref()
+    }
+
+    /// The unit reports are requested in.
+    pub fn unit(&self) -> Unit {
+        self.settings.unit.unwrap_or_default()
+    }
+
+    pub fn with_settings(&self, overrides: &Settings) -> Config {
+        Config {
+            settings: Settings {
+                unit: overrides.unit.or(self.settings.unit),
+                lang: overrides
+                    .lang
+                    .clone()
+                    .or_else(|| self.settings.lang.clone()),
+            },
+            ..self.clone()
+        }
+    }
+
+    pub fn keys(&self) -> &Keys {
+        &self.keys
+    }
+
+    /// The URL of `request` sent with the API key `key`.
+    pub fn url<T>(&self, request: &Request<T>, key: &str) -> Result<Url> {
+        let mut params = request.params.clone();
+        params.push(("APPID".to_string(), key.to_string()));
+        if request.api != Api::Geo {
+            params.append(&mut self.settings.format());
+        }
+
+        let mut base = self.base_url.as_str().to_string();
+        if !base.ends_with('/') {
+            base.push('/');
+        }
+        let api = match request.api {
+            Api::Data => format!("data/{}", self.api_version),
+            Api::OneCall => format!("data/{}", ONE_CALL_API_VERSION),
+            Api::Geo => format!("geo/{}", GEO_API_VERSION),
+        };
+        base.push_str(&format!("{}/{}", api, request.path));
+        Ok(Url::parse_with_params(&base, params)?)
+    }
+
+    /// The key of `request` in the caches, the same whichever API key it
+    /// is sent with.
+    pub fn cache_key<T>(&self, request: &Request<T>) -> Result<String> {
+        Ok(cache_key(&self.url(request, "")?))
+    }
+}
+
+/// Configures and creates a `Client` or an `AsyncClient`.
+#[derive(Debug)]
+pub struct ClientBuilder {
+    config: Config,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+}
+
+impl ClientBuilder {
+    pub(crate) fn new(key: &str) -> ClientBuilder {
+        ClientBuilder {
+            config: Config {
+                keys: Arc::new(Keys::single(key)),
+                settings: Settings::default(),
+                base_url: Url::parse(API_BASE).expect("valid default base url"),
+                api_version: API_VERSION.to_string(),
+                retry: RetryPolicy::none(),
+                rate_limiter: None,
+                cache: None,
+                disk_cache: None,
+                revalidations: Arc::default(),
+                coalescer: None,
+            },
+            connect_timeout: Some(Duration::from_secs(60)),
+            read_timeout: Some(Duration::from_secs(60)),
+        }
+    }
+
+    /// Default settings applied to every request made by the client.
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.config.settings = settings;
+        self
+    }
+
+    /// The root every request is sent to, `https://api.openweathermap.org/`
+    /// by default. Scheme, host, port and a path prefix can all be changed
+    /// to go through a proxy, a mirror or a local stand-in such as
+    /// `http://127.0.0.1:8080/owm/`; endpoint paths are appended to it.
+    pub fn base_url(mut self, url: Url) -> Self {
+        self.config.base_url = url;
+        self
+    }
+
+    /// The version segment of the weather data API, `2.5` by default, so
+    /// requests go to `{base_url}data/{version}/{endpoint}`. One Call 3.0
+    /// requests always go to `{base_url}data/3.0/` and geocoding requests to
+    /// `{base_url}geo/1.0/`.
+    pub fn api_version(mut self, version: &str) -> Self {
+        self.config.api_version = version.trim_matches('/').to_string();
+        self
+    }
+
+    /// How requests failing with a retryable error are retried, see
+    /// `RetryPolicy`. Requests are sent only once by default.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.config.retry = policy;
+        self
+    }
+
+    /// Limits the calls the client makes, see `RateLimit`. Every attempt of
+    /// a retried request counts as a call.
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.config.rate_limiter = Some(Arc::new(RateLimiter::new(&limit)));
+        self
+    }
+
+    /// Keeps successful responses in memory, see `Cache`. Clones of the
+    /// client, and clients made with `with_settings`, share the cache.
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.config.cache = Some(Arc::new(MemoryCache::new(cache)));
+        self
+    }
+
+    /// Keeps successful responses on disk, see `DiskCache`. Responses found
+    /// in the in-memory `Cache` are used before those on disk.
+    pub fn disk_cache(mut self, cache: DiskCache) -> Self {
+        self.config.disk_cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Sends requests with the keys of `pool` instead of the key the builder
+    /// was created with, see `KeyPool`. Clones of the client, and clients
+    /// made with `with_settings`, share the pool and its counters. Requests
+    /// to an API the pool has no key for, as with an empty pool, fail with
+    /// `Error::Input`.
+    pub fn key_pool(mut self, pool: KeyPool) -> Self {
+        self.config.keys = Arc::new(Keys::new(pool));
+        self
+    }
+
+    /// Whether concurrent identical requests, those with the same URL apart
+    /// from the key, share a single HTTP call, off by default. Clones of the
+    /// client, and clients made with `with_settings`, share their calls.
+    /// When the shared call fails, every caller gets `Error::Coalesced`.
+    pub fn coalesce_requests(mut self, coalesce: bool) -> Self {
+        self.config.coalescer = if coalesce {
+            Some(Arc::new(Coalescer::default()))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Timeout for establishing the connection, `None` to wait forever.
+    /// Only used by the default transports.
+    pub fn connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Timeout for reading the response, `None` to wait forever.
+    /// Only used by the default transports.
+    pub fn read_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Creates a blocking client using the default `http_req` transport.
+    #[cfg(feature = "blocking")]
+    pub fn build(self) -> Client {
+        let transport = HttpReqTransport::new(self.connect_timeout, self.read_timeout);
+        self.build_with_transport(transport)
+    }
+
+    /// Creates a blocking client sending its requests through `transport`.
+    #[cfg(feature = "blocking")]
+    pub fn build_with_transport<T: Transport>(self, transport: T) -> Client<T> {
+        Client::from_parts(self.config, transport)
+    }
+
+    /// Creates an async client using the default `reqwest` transport.
+    #[cfg(feature = "async")]
+    pub fn build_async(self) -> Result<AsyncClient> {
+        let transport = ReqwestTransport::new(self.connect_timeout, self.read_timeout)?;
+        Ok(self.build_async_with_transport(transport))
+    }
+
+    /// Creates an async client sending its requests through `transport`.
+    #[cfg(feature = "async")]
+    pub fn build_async_with_transport<T: AsyncTransport>(self, transport: T) -> AsyncClient<T> {
+        AsyncClient::from_parts(self.config, transport)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::ClientBuilder;
+    use crate::{Language, LocationSpecifier, Settings, Unit};
+
+    #[test]
+    fn url_contains_key_settings_and_params() {
+        let config = ClientBuilder::new("KEY")
+            .settings(Settings {
+                unit: Some(Unit::Metric),
+                lang: None,
+            })
+            .config;
+        let loc = LocationSpecifier::CityId("5037649".to_string());
+        let url = config
+            .url(&crate::request::current_weather(&loc).unwrap(), "KEY")
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.openweathermap.org/data/2.5/weather?id=5037649&APPID=KEY&units=metric"
+        );
+    }
+
+    #[test]
+    fn base_url_and_version_are_configurable() {
+        let loc = LocationSpecifier::CityId("5037649".to_string());
+        let request = crate::request::current_weather(&loc).unwrap();
+
+        let config = ClientBuilder::new("KEY")
+            .base_url(url::Url::parse("http://127.0.0.1:8080").unwrap())
+            .config;
+        let url = config.url(&request, "KEY").unwrap();
+        assert_eq!(
+            url.as_str(),
+            "http://127.0.0.1:8080/data/2.5/weather?id=5037649&APPID=KEY"
+        );
+
+        let config = ClientBuilder::new("KEY")
+            .base_url(url::Url::parse("https://gateway.internal/owm").unwrap())
+            .api_version("3.0")
+            .config;
+        let url = config.url(&request, "KEY").unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://gateway.internal/owm/data/3.0/weather?id=5037649&APPID=KEY"
+        );
+    }
+
+    #[test]
+    fn with_settings_overrides_only_given_fields() {
+        let config = ClientBuilder::new("KEY")
+            .settings(Settings {
+                unit: Some(Unit::Metric),
+                lang: Some(Language::German),
+            })
+            .config;
+        let imperial = config.with_settings(&Settings {
+            unit: Some(Unit::Imperial),
+            lang: None,
+        });
+        let loc = LocationSpecifier::CityId("5037649".to_string());
+        let url = imperial
+            .url(&crate::request::current_weather(&loc).unwrap(), "KEY")
+            .unwrap();
+        assert_eq!(
+            url.query(),
+            Some("id=5037649&APPID=KEY&units=imperial&lang=de")
+        );
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "blocking")]
+use std::sync::Condvar;
+
+use crate::{Error, HttpResponse, Result};
+
+/// The outcome of a request as handed to every caller waiting on it.
+type Shared = std::result::Result<HttpResponse, Arc<Error>>;
+
+/// Splits the outcome of a request between its waiters and the caller that
+/// sent it. Errors cannot be cloned, so everyone gets `Error::Coalesced`.
+fn share(result: Result<HttpResponse>) -> (Shared, Result<HttpResponse>) {
+    match result {
+        Ok(res) => (Ok(res.clone()), Ok(res)),
+        Err(err) => {
+            let err = Arc::new(err);
+            (Err(err.clone()), Err(Error::Coalesced(err)))
+        }
+    }
+}
+
+fn unshare(shared: Shared) -> Result<HttpResponse> {
+    shared.map_err(Error::Coalesced)
+}
+
+#[cfg(feature = "blocking")]
+enum State {
+    Pending,
+    Done(Shared),
+    /// The sending caller panicked
+    Abandoned,
+}
+
+#[cfg(feature = "blocking")]
+struct Flight {
+    state: Mutex<State>,
+    done: Condvar,
+}
+
+/// Lets concurrent identical requests share a single HTTP call: the first
+/// caller sends it, the others wait for and get a copy of its response. A
+/// caller never waits on a request that was sent before it asked.
+#[derive(Default)]
+pub(crate) struct Coalescer {
+    #[cfg(feature = "blocking")]
+    flights: Mutex<HashMap<String, Arc<Flight>>>,
+    #[cfg(feature = "async")]
+    async_flights: Mutex<HashMap<String, tokio::sync::watch::Sender<Option<Shared>>>>,
+}
+
+impl std::fmt::Debug for Coalescer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Coalescer").finish()
+    }
+}
+
+/// Removes the flight of a blocking caller from its `Coalescer`, letting
+/// the waiters send the request themselves if the caller unwinds first.
+#[cfg(feature = "blocking")]
+struct Leader<'a> {
+    coalescer: &'a Coalescer,
+    key: &'a str,
+    flight: Arc<Flight>,
+}
+
+#[cfg(feature = "blocking")]
+impl Leader<'_> {
+    fn finish(self, result: Result<HttpResponse>) -> Result<HttpResponse> {
+        self.remove();
+        if Arc::strong_count(&self.flight) == 1 {
+            return result;
+        }
+        let (shared, own) = share(result);
+        *self.flight.state.lock().unwrap() = State::Done(shared);
+        self.flight.done.notify_all();
+        own
+    }
+
+    fn remove(&self) {
+        let mut flights = self.coalescer.flights.lock().unwrap();
+        if flights
+            .get(self.key)
+            .is_some_and(|flight| Arc::ptr_eq(flight, &self.flight))
+        {
+            flights.remove(self.key);
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        self.remove();
+        let mut state = self.flight.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let State::Pending = *state {
+            *state = State::Abandoned;
+            self.flight.done.notify_all();
+        }
+    }
+}
+
+/// Removes the flight of an async caller from its `Coalescer`, which closes
+/// the channel of the waiters if the caller's future is dropped first.
+#[cfg(feature = "async")]
+struct AsyncLeader<'a> {
+    coalescer: &'a Coalescer,
+    key: &'a str,
+    sender: tokio::sync::watch::Sender<Option<Shared>>,
+}
+
+#[cfg(feature = "async")]
+impl Drop for AsyncLeader<'_> {
+    fn drop(&mut self) {
+        let mut flights = self.coalescer.async_flights.lock().unwrap();
+        if flights
+            .get(self.key)
+            .is_some_and(|sender| sender.same_channel(&self.sender))
+        {
+            flights.remove(self.key);
+        }
+    }
+}
+
+impl Coalescer {
+    /// Runs `send` for the request with the cache key `key`, unless an
+    /// identical request is already in flight.
+    #[cfg(feature = "blocking")]
+    pub fn run(
+        &self,
+        key: &str,
+        send: impl FnOnce() -> Result<HttpResponse>,
+    ) -> Result<HttpResponse> {
+        let (flight, leading) = {
+            let mut flights = self.flights.lock().unwrap();
+            match flights.get(key) {
+                Some(flight) => (flight.clone(), false),
+                None => {
+                    let flight = Arc::new(Flight {
+                        state: Mutex::new(State::Pending),
+                        done: Condvar::new(),
+                    });
+                    flights.insert(key.to_string(), flight.clone());
+                    (flight, true)
+                }
+            }
+        };
+
+        if leading {
+            let leader = Leader {
+                coalescer: self,
+                key,
+                flight,
+            };
+            return leader.finish(send());
+        }
+        let mut state = flight.state.lock().unwrap();
+        loop {
+            match &*state {
+                State::Pending => state = flight.done.wait(state).unwrap(),
+                State::Done(shared) => return unshare(shared.clone()),
+                State::Abandoned => break,
+            }
+        }
+        drop(state);
+        send()
+    }
+
+    /// The async counterpart of `run`.
+    #[cfg(feature = "async")]
+    pub async fn run_async<F>(&self, key: &str, send: F) -> Result<HttpResponse>
+    where
+        F: std::future::Future<Output = Result<HttpResponse>>,
+    {
+        let waiting = {
+            let mut flights = self.async_flights.lock().unwrap();
+            match flights.get(key) {
+                Some(sender) => Err(sender.subscribe()),
+                None => {
+                    let (sender, _) = tokio::sync::watch::channel(None);
+                    flights.insert(key.to_string(), sender.clone());
+                    Ok(sender)
+                }
+            }
+        };
+
+        let sender = match waiting {
+            Ok(sender) => sender,
+            Err(mut receiver) => {
+                let shared = match receiver.wait_for(Option::is_some).await {
+                    Ok(shared) => shared.clone(),
+                    Err(_) => None,
+                };
+                return match shared {
+                    Some(shared) => unshare(shared),
+                    None => send.await,
+                };
+            }
+        };
+
+        let leader = AsyncLeader {
+            coalescer: self,
+            key,
+            sender,
+        };
+        let result = send.await;
+        let sender = leader.sender.clone();
+        drop(leader);
+        if sender.receiver_count() == 0 {
+            return result;
+        }
+        let (shared, own) = share(result);
+        sender.send_replace(Some(shared));
+        own
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::Coalescer;
+    use crate::{Error, HttpResponse};
+
+    fn ok() -> crate::Result<HttpResponse> {
+        Ok(HttpResponse {
+            status: 200,
+            ..Default::default()
+        })
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn concurrent_callers_share_one_call() {
+        use std::sync::{Arc, Barrier};
+        use std::thread;
+        use std::time::Duration;
+
+        let coalescer = Arc::new(Coalescer::default());
+        let calls = Arc::new(AtomicUsize::new(0));
+        let barrier = Arc::new(Barrier::new(4));
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let (coalescer, calls, barrier) =
+                    (coalescer.clone(), calls.clone(), barrier.clone());
+                thread::spawn(move || {
+                    barrier.wait();
+                    coalescer.run("key", || {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(100));
+                        ok()
+                    })
+                })
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap().unwrap().status, 200);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn a_lone_caller_keeps_its_error() {
+        let coalescer = Coalescer::default();
+        let err = coalescer
+            .run("key", || Err(Error::Input { msg: "no".into() }))
+            .unwrap_err();
+        assert!(matches!(err, Error::Input { .. }));
+        assert!(coalescer.flights.lock().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn concurrent_futures_share_one_call() {
+        let coalescer = Coalescer::default();
+        let calls = AtomicUsize::new(0);
+        let send = || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            Err(Error::Input { msg: "no".into() })
+        };
+        let (a, b) = tokio::join!(
+            coalescer.run_async("key", send()),
+            coalescer.run_async("key", send())
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        for err in [a.unwrap_err(), b.unwrap_err()] {
+            assert!(matches!(err, Error::Coalesced(_)));
+            assert_eq!(err.kind(), crate::ErrorKind::Input);
+        }
+        assert_eq!(
+            coalescer
+                .run_async("key", async { ok() })
+                .await
+                .unwrap()
+                .status,
+            200
+        );
+    }
+}
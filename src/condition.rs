@@ -0,0 +1,338 @@
+use std::hash::{Hash, Hasher};
+
+use url::Url;
+
+use crate::{Result, Weather};
+
+static ICON_BASE: &str = "https://openweathermap.org/img/wn/";
+
+macro_rules! condition_codes {
+    ($($(#[$doc:meta])* $name:ident = $code:literal,)*) => {
+        /// A weather condition as listed in OpenWeatherMap's condition code
+        /// table, codes missing from the table being kept as `Other`.
+        ///
+        /// Conditions compare and hash by their code, so `Other(800)`, which
+        /// `From<u32>` never makes, equals `ClearSky`.
+        #[derive(Debug, Clone, Copy)]
+        pub enum ConditionCode {
+            $($(#[$doc])* $name,)*
+            /// A code the crate does not know
+            Other(u32),
+        }
+
+        impl From<u32> for ConditionCode {
+            fn from(code: u32) -> ConditionCode {
+                match code {
+                    $($code => ConditionCode::$name,)*
+                    _ => ConditionCode::Other(code),
+                }
+            }
+        }
+
+        impl From<ConditionCode> for u32 {
+            fn from(condition: ConditionCode) -> u32 {
+                match condition {
+                    $(ConditionCode::$name => $code,)*
+                    ConditionCode::Other(code) => code,
+                }
+            }
+        }
+    };
+}
+
+condition_codes! {
+    ThunderstormWithLightRain = 200,
+    ThunderstormWithRain = 201,
+    ThunderstormWithHeavyRain = 202,
+    LightThunderstorm = 210,
+    Thunderstorm = 211,
+    HeavyThunderstorm = 212,
+    RaggedThunderstorm = 221,
+    ThunderstormWithLightDrizzle = 230,
+    ThunderstormWithDrizzle = 231,
+    ThunderstormWithHeavyDrizzle = 232,
+    LightDrizzle = 300,
+    Drizzle = 301,
+    HeavyDrizzle = 302,
+    LightDrizzleRain = 310,
+    DrizzleRain = 311,
+    HeavyDrizzleRain = 312,
+    ShowerRainAndDrizzle = 313,
+    HeavyShowerRainAndDrizzle = 314,
+    ShowerDrizzle = 321,
+    LightRain = 500,
+    ModerateRain = 501,
+    HeavyRain = 502,
+    VeryHeavyRain = 503,
+    ExtremeRain = 504,
+    FreezingRain = 511,
+    LightShowerRain = 520,
+    ShowerRain = 521,
+    HeavyShowerRain = 522,
+    RaggedShowerRain = 531,
+    LightSnow = 600,
+    Snow = 601,
+    HeavySnow = 602,
+    Sleet = 611,
+    LightShowerSleet = 612,
+    ShowerSleet = 613,
+    LightRainAndSnow = 615,
+    RainAndSnow = 616,
+    LightShowerSnow = 620,
+    ShowerSnow = 621,
+    HeavyShowerSnow = 622,
+    Mist = 701,
+    Smoke = 711,
+    Haze = 721,
+    /// Sand or dust whirls
+    DustWhirls = 731,
+    Fog = 741,
+    Sand = 751,
+    Dust = 761,
+    VolcanicAsh = 762,
+    Squalls = 771,
+    Tornado = 781,
+    ClearSky = 800,
+    /// 11-25% cloud cover
+    FewClouds = 801,
+    /// 25-50% cloud cover
+    ScatteredClouds = 802,
+    /// 51-84% cloud cover
+    BrokenClouds = 803,
+    /// 85-100% cloud cover
+    OvercastClouds = 804,
+}
+
+impl PartialEq for ConditionCode {
+    fn eq(&self, other: &ConditionCode) -> bool {
+        u32::from(*self) == u32::from(*other)
+    }
+}
+
+impl Eq for ConditionCode {}
+
+impl Hash for ConditionCode {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        u32::from(*self).hash(state);
+    }
+}
+
+/// The group a `ConditionCode` belongs to, given by its hundreds digit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConditionGroup {
+    /// 2xx
+    Thunderstorm,
+    /// 3xx
+    Drizzle,
+    /// 5xx
+    Rain,
+    /// 6xx
+    Snow,
+    /// 7xx: mist, smoke, haze, dust, fog, ash, squalls and tornadoes
+    Atmosphere,
+    /// 800
+    Clear,
+    /// 80x
+    Clouds,
+    Unknown,
+}
+
+/// How strong the precipitation of a `ConditionCode` is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Intensity {
+    Light,
+    Moderate,
+    Heavy,
+    Extreme,
+}
+
+impl ConditionCode {
+    pub fn code(self) -> u32 {
+        self.into()
+    }
+
+    pub fn group(self) -> ConditionGroup {
+        match self.code() {
+            200..=299 => ConditionGroup::Thunderstorm,
+            300..=399 => ConditionGroup::Drizzle,
+            500..=599 => ConditionGroup::Rain,
+            600..=699 => ConditionGroup::Snow,
+            700..=799 => ConditionGroup::Atmosphere,
+            800 => ConditionGroup::Clear,
+            801..=899 => ConditionGroup::Clouds,
+            _ => ConditionGroup::Unknown,
+        }
+    }
+
+    /// The intensity of thunderstorms and precipitation, `None` for the
+    /// other groups and unknown codes.
+    pub fn intensity(self) -> Option<Intensity> {
+        use ConditionCode::*;
+        match ConditionCode::from(self.code()) {
+            ThunderstormWithLightRain
+            | LightThunderstorm
+            | ThunderstormWithLightDrizzle
+            | LightDrizzle
+            | LightDrizzleRain
+            | LightRain
+            | LightShowerRain
+            | LightSnow
+            | LightShowerSleet
+            | LightRainAndSnow
+            | LightShowerSnow => Some(Intensity::Light),
+            ThunderstormWithRain
+            | Thunderstorm
+            | RaggedThunderstorm
+            | ThunderstormWithDrizzle
+            | Drizzle
+            | DrizzleRain
+            | ShowerRainAndDrizzle
+            | ShowerDrizzle
+            | ModerateRain
+            | FreezingRain
+            | ShowerRain
+            | RaggedShowerRain
+            | Snow
+            | Sleet
+            | ShowerSleet
+            | RainAndSnow
+            | ShowerSnow => Some(Intensity::Moderate),
+            ThunderstormWithHeavyRain
+            | HeavyThunderstorm
+            | ThunderstormWithHeavyDrizzle
+            | HeavyDrizzle
+            | HeavyDrizzleRain
+            | HeavyShowerRainAndDrizzle
+            | HeavyRain
+            | VeryHeavyRain
+            | HeavyShowerRain
+            | HeavySnow
+            | HeavyShowerSnow => Some(Intensity::Heavy),
+            ExtremeRain => Some(Intensity::Extreme),
+            _ => None,
+        }
+    }
+
+    /// A unicode weather symbol for the condition, the clear and lightly
+    /// clouded ones differing between day and night.
+    pub fn symbol(self, day: bool) -> &'static str {
+        use ConditionCode::*;
+        match ConditionCode::from(self.code()) {
+            ClearSky if day => "☀️",
+            ClearSky => "🌙",
+            FewClouds if day => "🌤️",
+            ScatteredClouds if day => "⛅",
+            FewClouds | ScatteredClouds => "☁️",
+            BrokenClouds => "🌥️",
+            OvercastClouds => "☁️",
+            Tornado => "🌪️",
+            Squalls => "💨",
+            FreezingRain | Sleet | LightShowerSleet | ShowerSleet => "🌨️",
+            LightRainAndSnow | RainAndSnow => "🌨️",
+            _ => match self.group() {
+                ConditionGroup::Thunderstorm => "⛈️",
+                ConditionGroup::Drizzle => "🌦️",
+                ConditionGroup::Rain => "🌧️",
+                ConditionGroup::Snow => "❄️",
+                ConditionGroup::Atmosphere => "🌫️",
+                _ => "❓",
+            },
+        }
+    }
+}
+
+/// The resolution of a condition icon, 50x50 pixels at `X1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconSize {
+    X1,
+    X2,
+    X4,
+}
+
+impl Weather {
+    pub fn condition(&self) -> ConditionCode {
+        self.id.into()
+    }
+
+    /// Whether the condition was observed in daylight, read from the `d` or
+    /// `n` suffix of the icon. `None` if the icon has neither.
+    pub fn is_day(&self) -> Option<bool> {
+        if self.icon.ends_with('d') {
+            Some(true)
+        } else if self.icon.ends_with('n') {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// The symbol of the condition, using the day variant when the time of
+    /// day is unknown.
+    pub fn symbol(&self) -> &'static str {
+        self.condition().symbol(self.is_day().unwrap_or(true))
+    }
+
+    /// Where OpenWeatherMap serves the PNG of this condition's icon.
+    pub fn icon_url(&self, size: IconSize) -> Result<Url> {
+        let scale = match size {
+            IconSize::X1 => "",
+            IconSize::X2 => "@2x",
+            IconSize::X4 => "@4x",
+        };
+        Ok(Url::parse(ICON_BASE)?.join(&format!("{}{}.png", self.icon, scale))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ConditionCode, ConditionGroup, IconSize, Intensity, Weather};
+
+    fn weather(id: u32, icon: &str) -> Weather {
+        Weather {
+            id,
+            icon: icon.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn classifies_codes() {
+        let rain = weather(502, "10n");
+        assert_eq!(rain.condition(), ConditionCode::HeavyRain);
+        assert_eq!(rain.condition().group(), ConditionGroup::Rain);
+        assert_eq!(rain.condition().intensity(), Some(Intensity::Heavy));
+        assert_eq!(rain.is_day(), Some(false));
+
+        assert_eq!(ConditionCode::from(804).group(), ConditionGroup::Clouds);
+        assert_eq!(ConditionCode::Fog.intensity(), None);
+        assert_eq!(ConditionCode::from(999), ConditionCode::Other(999));
+        assert_eq!(ConditionCode::Other(800), ConditionCode::ClearSky);
+        assert_eq!(ConditionCode::Other(800).symbol(true), "☀️");
+        assert_eq!(
+            ConditionCode::Other(502).intensity(),
+            Some(Intensity::Heavy)
+        );
+        let codes: std::collections::HashSet<_> =
+            [ConditionCode::Other(800), ConditionCode::ClearSky].into();
+        assert_eq!(codes.len(), 1);
+        assert_eq!(ConditionCode::from(999).group(), ConditionGroup::Unknown);
+        assert_eq!(u32::from(ConditionCode::Tornado), 781);
+    }
+
+    #[test]
+    fn symbols_and_icons() {
+        assert_eq!(weather(800, "01d").symbol(), "☀️");
+        assert_eq!(weather(800, "01n").symbol(), "🌙");
+        assert_eq!(weather(211, "11d").symbol(), "⛈️");
+
+        let clouds = weather(803, "04d");
+        assert_eq!(
+            clouds.icon_url(IconSize::X1).unwrap().as_str(),
+            "https://openweathermap.org/img/wn/04d.png"
+        );
+        assert_eq!(
+            clouds.icon_url(IconSize::X4).unwrap().as_str(),
+            "https://openweathermap.org/img/wn/04d@4x.png"
+        );
+    }
+}
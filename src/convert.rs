@@ -0,0 +1,356 @@
+use crate::weather_types::*;
+use crate::{Speed, Temperature, Unit};
+
+/// Rewrites the temperatures and speeds of a report, or part of one, from
+/// one `Unit` to another without asking the API again. Pressure and
+/// precipitation do not depend on the unit and are left as they are.
+///
+/// Reports that know their unit also have a `to_unit` method:
+///
+/// ```
+/// use openweather::{Unit, WeatherReportCurrent};
+///
+/// let mut report = WeatherReportCurrent::default();
+/// report.main.temp = 293.15;
+/// let metric = report.to_unit(Unit::Metric);
+/// assert_eq!(metric.unit, Unit::Metric);
+/// assert!((metric.main.temp - 20.0).abs() < 1e-4);
+/// ```
+pub trait ConvertUnit {
+    fn convert_unit(&mut self, from: Unit, to: Unit);
+}
+
+fn temperature(value: &mut f32, from: Unit, to: Unit) {
+    *value = Temperature::new((*value).into(), from.temperature_scale()).get(to.temperature_scale())
+        as f32;
+}
+
+fn speed(value: &mut f32, from: Unit, to: Unit) {
+    *value = Speed::new((*value).into(), from.speed_unit()).get(to.speed_unit()) as f32;
+}
+
+impl<T: ConvertUnit> ConvertUnit for Option<T> {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        if let Some(value) = self {
+            value.convert_unit(from, to);
+        }
+    }
+}
+
+impl<T: ConvertUnit> ConvertUnit for Vec<T> {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        for value in self {
+            value.convert_unit(from, to);
+        }
+    }
+}
+
+impl ConvertUnit for Main {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        temperature(&mut self.temp, from, to);
+        temperature(&mut self.temp_min, from, to);
+        temperature(&mut self.temp_max, from, to);
+    }
+}
+
+impl ConvertUnit for Wind {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        speed(&mut self.speed, from, to);
+        if let Some(gust) = &mut self.gust {
+            speed(gust, from, to);
+        }
+    }
+}
+
+impl ConvertUnit for TempDaily {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        for value in [
+            &mut self.day,
+            &mut self.min,
+            &mut self.max,
+            &mut self.night,
+            &mut self.eve,
+            &mut self.morn,
+        ] {
+            temperature(value, from, to);
+        }
+    }
+}
+
+impl ConvertUnit for WeatherReportOneCallTemp {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        for value in [
+            &mut self.day,
+            &mut self.min,
+            &mut self.max,
+            &mut self.night,
+            &mut self.eve,
+            &mut self.morn,
+        ] {
+            temperature(value, from, to);
+        }
+    }
+}
+
+impl ConvertUnit for WeatherReportOneCallFeelsLike {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        for value in [
+            &mut self.day,
+            &mut self.night,
+            &mut self.eve,
+            &mut self.morn,
+        ] {
+            temperature(value, from, to);
+        }
+    }
+}
+
+impl ConvertUnit for TimeSliceHourly {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.main.convert_unit(from, to);
+        self.wind.convert_unit(from, to);
+    }
+}
+
+impl ConvertUnit for TimeSliceDaily {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.temp.convert_unit(from, to);
+        speed(&mut self.speed, from, to);
+    }
+}
+
+impl ConvertUnit for WeatherReportHistoricalElement {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.main.convert_unit(from, to);
+        self.wind.convert_unit(from, to);
+    }
+}
+
+impl ConvertUnit for CityWeather {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.main.convert_unit(from, to);
+        self.wind.convert_unit(from, to);
+    }
+}
+
+impl ConvertUnit for WeatherReportOneCallCurrent {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        temperature(&mut self.temp, from, to);
+        temperature(&mut self.feels_like, from, to);
+        temperature(&mut self.dew_point, from, to);
+        speed(&mut self.wind_speed, from, to);
+        if let Some(gust) = &mut self.wind_gust {
+            speed(gust, from, to);
+        }
+    }
+}
+
+impl ConvertUnit for WeatherReportOneCallHourly {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        temperature(&mut self.temp, from, to);
+        temperature(&mut self.feels_like, from, to);
+        temperature(&mut self.dew_point, from, to);
+        speed(&mut self.wind_speed, from, to);
+        if let Some(gust) = &mut self.wind_gust {
+            speed(gust, from, to);
+        }
+    }
+}
+
+impl ConvertUnit for WeatherReportOneCallDaily {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.temp.convert_unit(from, to);
+        self.feels_like.convert_unit(from, to);
+        temperature(&mut self.dew_point, from, to);
+        speed(&mut self.wind_speed, from, to);
+        if let Some(gust) = &mut self.wind_gust {
+            speed(gust, from, to);
+        }
+    }
+}
+
+impl ConvertUnit for DaySummaryTemperature {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        for value in [
+            &mut self.min,
+            &mut self.max,
+            &mut self.morning,
+            &mut self.afternoon,
+            &mut self.evening,
+            &mut self.night,
+        ] {
+            temperature(value, from, to);
+        }
+    }
+}
+
+/// Also rewrites `units`, which the report names its unit with.
+impl ConvertUnit for WeatherReportDaySummary {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.temperature.convert_unit(from, to);
+        speed(&mut self.wind.max.speed, from, to);
+        let units = match to {
+            Unit::Metric => "metric",
+            Unit::Standard => "standard",
+            Unit::Imperial => "imperial",
+        };
+        self.units = units.to_string();
+    }
+}
+
+/// An accumulated temperature is the sum of `count` temperatures, so each is
+/// converted on its own.
+impl ConvertUnit for WeatherAccumulatedTemperatureElement {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        if self.count == 0 {
+            return;
+        }
+        let count = self.count as f32;
+        let mut mean = self.temp / count;
+        temperature(&mut mean, from, to);
+        self.temp = mean * count;
+    }
+}
+
+impl ConvertUnit for WeatherAccumulatedTemperature {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.list.convert_unit(from, to);
+    }
+}
+
+impl ConvertUnit for WeatherReportCurrent {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.main.convert_unit(from, to);
+        self.wind.convert_unit(from, to);
+    }
+}
+
+impl ConvertUnit for WeatherReportCities {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.list.convert_unit(from, to);
+    }
+}
+
+impl ConvertUnit for WeatherReport5Day {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.list.convert_unit(from, to);
+    }
+}
+
+impl ConvertUnit for WeatherReport16Day {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.list.convert_unit(from, to);
+    }
+}
+
+impl ConvertUnit for WeatherReportHistorical {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.list.convert_unit(from, to);
+    }
+}
+
+impl ConvertUnit for WeatherReportOneCall {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.current.convert_unit(from, to);
+        self.daily.convert_unit(from, to);
+    }
+}
+
+impl ConvertUnit for WeatherReportOneCall3 {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.current.convert_unit(from, to);
+        self.hourly.convert_unit(from, to);
+        self.daily.convert_unit(from, to);
+    }
+}
+
+impl ConvertUnit for WeatherReportOneCallHistorical {
+    fn convert_unit(&mut self, from: Unit, to: Unit) {
+        self.current.convert_unit(from, to);
+        self.hourly.convert_unit(from, to);
+    }
+}
+
+macro_rules! to_unit {
+    ($($report:ty),*) => {
+        $(impl $report {
+            /// Converts the report from the unit it was requested in to
+            /// `unit`, see `ConvertUnit`.
+            pub fn to_unit(mut self, unit: Unit) -> Self {
+                let from = self.unit;
+                self.convert_unit(from, unit);
+                self.unit = unit;
+                self
+            }
+        })*
+    };
+}
+
+to_unit!(
+    WeatherReportCurrent,
+    WeatherReportCities,
+    WeatherReport5Day,
+    WeatherReport16Day,
+    WeatherReportHistorical,
+    WeatherReportOneCall,
+    WeatherReportOneCall3,
+    WeatherReportOneCallHistorical
+);
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ConvertUnit, Unit, WeatherAccumulatedTemperature, WeatherReport5Day,
+        WeatherReportDaySummary,
+    };
+
+    fn forecast() -> WeatherReport5Day {
+        serde_json::from_str(include_str!("../tests/fixtures/forecast.json")).unwrap()
+    }
+
+    #[test]
+    fn converts_between_units() {
+        let standard = forecast();
+        let imperial = forecast().to_unit(Unit::Imperial);
+        assert_eq!(imperial.unit, Unit::Imperial);
+        let (kelvin, fahrenheit) = (&standard.list[0], &imperial.list[0]);
+        let expected = (kelvin.main.temp - 273.15) * 9.0 / 5.0 + 32.0;
+        assert!((fahrenheit.main.temp - expected).abs() < 1e-3);
+        assert!((fahrenheit.wind.speed - kelvin.wind.speed / 0.44704).abs() < 1e-3);
+        assert_eq!(fahrenheit.main.pressure, kelvin.main.pressure);
+
+        let back = imperial.to_unit(Unit::Standard);
+        assert!((back.list[0].main.temp - kelvin.main.temp).abs() < 1e-3);
+    }
+
+    #[test]
+    fn same_unit_is_unchanged() {
+        assert_eq!(forecast().to_unit(Unit::Standard), forecast());
+        let metric = forecast().to_unit(Unit::Metric);
+        assert_eq!(metric.list[0].wind, forecast().list[0].wind);
+    }
+
+    #[test]
+    fn converts_day_summaries() {
+        let mut summary: WeatherReportDaySummary =
+            serde_json::from_str(include_str!("../tests/fixtures/day_summary.json")).unwrap();
+        summary.convert_unit(Unit::Standard, Unit::Imperial);
+        assert_eq!(summary.units, "imperial");
+        let expected = (286.48 - 273.15) * 9.0 / 5.0 + 32.0;
+        assert!((summary.temperature.min - expected).abs() < 1e-3);
+        assert!((summary.wind.max.speed - 8.7 / 0.44704).abs() < 1e-3);
+        assert_eq!(summary.pressure.afternoon, 1015.0);
+    }
+
+    #[test]
+    fn converts_accumulated_temperatures() {
+        let mut accumulated: WeatherAccumulatedTemperature = serde_json::from_str(include_str!(
+            "../tests/fixtures/accumulated_temperature.json"
+        ))
+        .unwrap();
+        accumulated.convert_unit(Unit::Metric, Unit::Imperial);
+        let day = &accumulated.list[0];
+        assert_eq!(day.count, 24);
+        assert!((day.temp - (23.6 * 9.0 / 5.0 + 32.0) * 24.0).abs() < 1e-2);
+    }
+}
@@ -0,0 +1,359 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::cache::default_ttls;
+use crate::{Endpoint, HttpResponse, Timestamp};
+
+/// A cache of successful responses kept as JSON files in a directory, so
+/// short-lived processes reuse what an earlier run fetched, see
+/// `ClientBuilder::disk_cache`.
+///
+/// Entries are keyed like the in-memory `Cache` and are fresh for the same
+/// per endpoint times to live. Past that an entry can still be used:
+///
+/// - within the `stale_while_revalidate` window it is returned right away
+///   while a fresh copy is fetched in the background,
+/// - within the `stale_if_error` window it is returned when fetching a fresh
+///   copy fails with a retryable error, e.g. when the API is unreachable.
+///
+/// Both windows are zero by default. Each entry stores the raw body and the
+/// time it was fetched.
+///
+/// ```
+/// use std::time::Duration;
+/// use openweather::DiskCache;
+///
+/// let cache = DiskCache::new("/var/cache/weather")
+///     .stale_while_revalidate(Duration::from_secs(5 * 60))
+///     .stale_if_error(Duration::from_secs(24 * 60 * 60));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskCache {
+    dir: PathBuf,
+    ttls: HashMap<Endpoint, Duration>,
+    stale_while_revalidate: Duration,
+    stale_if_error: Duration,
+}
+
+/// How usable a stored entry is, from its age.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Freshness {
+    Fresh,
+    /// Usable while a fresh copy is fetched
+    Revalidate,
+    /// Usable only when fetching a fresh copy fails
+    IfError,
+}
+
+/// A response read back from a `DiskCache`.
+#[derive(Debug)]
+pub(crate) struct StoredResponse {
+    pub response: HttpResponse,
+    pub freshness: Freshness,
+}
+
+/// The file format of an entry.
+#[derive(Debug, Serialize, Deserialize)]
+struct Blob {
+    key: String,
+    fetched_at: Timestamp,
+    status: u16,
+    body: String,
+}
+
+impl DiskCache {
+    /// Keeps entries in `dir`, created on the first store.
+    pub fn new(dir: impl Into<PathBuf>) -> DiskCache {
+        DiskCache {
+            dir: dir.into(),
+            ttls: default_ttls(),
+            stale_while_revalidate: Duration::ZERO,
+            stale_if_error: Duration::ZERO,
+        }
+    }
+
+    /// How long responses of `endpoint` are fresh.
+    pub fn ttl(mut self, endpoint: Endpoint, ttl: Duration) -> Self {
+        self.ttls.insert(endpoint, ttl);
+        self
+    }
+
+    /// How long after going stale an entry is returned while a fresh copy is
+    /// fetched in the background.
+    pub fn stale_while_revalidate(mut self, window: Duration) -> Self {
+        self.stale_while_revalidate = window;
+        self
+    }
+
+    /// How long after going stale an entry is returned when fetching a fresh
+    /// copy fails.
+    pub fn stale_if_error(mut self, window: Duration) -> Self {
+        self.stale_if_error = window;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Removes every entry and any temporary file left by an interrupted
+    /// store, leaving other files in the directory alone.
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::read_dir(&self.dir) {
+            Ok(entries) => {
+                for entry in entries {
+                    let path = entry?.path();
+                    if is_entry(&path) {
+                        fs::remove_file(path)?;
+                    }
+                }
+                Ok(())
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.json", fnv1a(key.as_bytes())))
+    }
+
+    /// The entry stored under `key` for a request of `endpoint`, `None` when
+    /// there is none or it is too old to be used at all.
+    pub(crate) fn load(&self, key: &str, endpoint: Endpoint) -> Option<StoredResponse> {
+        self.load_at(key, endpoint, SystemTime::now())
+    }
+
+    fn load_at(&self, key: &str, endpoint: Endpoint, now: SystemTime) -> Option<StoredResponse> {
+        let contents = fs::read(self.path(key)).ok()?;
+        let blob: Blob = match serde_json::from_slice(&contents) {
+            Ok(blob) => blob,
+            Err(err) => {
+                debug!("Ignoring unreadable cache entry for {}: {}", key, err);
+                return None;
+            }
+        };
+        if blob.key != key {
+            return None;
+        }
+
+        let fetched_at = SystemTime::try_from(blob.fetched_at).ok()?;
+        let age = now.duration_since(fetched_at).unwrap_or_default();
+        let ttl = self.ttls.get(&endpoint).copied().unwrap_or_default();
+        let freshness = if age < ttl {
+            Freshness::Fresh
+        } else if age < ttl + self.stale_while_revalidate {
+            Freshness::Revalidate
+        } else if age < ttl + self.stale_if_error {
+            Freshness::IfError
+        } else {
+            return None;
+        };
+        Some(StoredResponse {
+            response: HttpResponse {
+                status: blob.status,
+                headers: vec![],
+                body: blob.body.into_bytes(),
+            },
+            freshness,
+        })
+    }
+
+    /// Stores `response` under `key`. Failures are logged and otherwise
+    /// ignored, the response having been fetched anyway.
+    pub(crate) fn store(&self, key: &str, response: &HttpResponse) {
+        self.store_at(key, response, SystemTime::now())
+    }
+
+    fn store_at(&self, key: &str, response: &HttpResponse, now: SystemTime) {
+        let blob = Blob {
+            key: key.to_string(),
+            fetched_at: now.into(),
+            status: response.status,
+            body: String::from_utf8_lossy(&response.body).into_owned(),
+        };
+        if let Err(err) = self.write(key, &blob) {
+            debug!("Could not store cache entry for {}: {}", key, err);
+        }
+    }
+
+    /// Writes through a temporary file of its own, so concurrent readers
+    /// never see a partial entry, even with other writers of the same key.
+    fn write(&self, key: &str, blob: &Blob) -> io::Result<()> {
+        static WRITES: AtomicU64 = AtomicU64::new(0);
+
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let tmp = path.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            WRITES.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&tmp, serde_json::to_vec(blob)?)?;
+        fs::rename(&tmp, &path)
+    }
+}
+
+/// Whether `path` is named like an entry file, `<hash>.json`, or like the
+/// temporary file of one, `<hash>.<pid>.<n>.tmp`, see `DiskCache::write`.
+fn is_entry(path: &Path) -> bool {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+    let (hash, rest) = name.split_at(name.find('.').unwrap_or(name.len()));
+    let hashed = hash.len() == 16 && hash.bytes().all(|byte| byte.is_ascii_hexdigit());
+    hashed && (rest == ".json" || (rest.len() > ".tmp".len() && rest.ends_with(".tmp")))
+}
+
+/// The keys of the entries being revalidated in the background, so a stale
+/// entry is fetched once however many requests hit it in the meantime.
+#[derive(Debug, Default)]
+pub(crate) struct Revalidations {
+    pending: Mutex<HashSet<String>>,
+}
+
+impl Revalidations {
+    /// Marks `key` as being revalidated until the returned guard is dropped,
+    /// `None` when it already is.
+    pub fn start(self: &Arc<Self>, key: &str) -> Option<Revalidation> {
+        if !self.pending.lock().unwrap().insert(key.to_string()) {
+            return None;
+        }
+        Some(Revalidation {
+            revalidations: self.clone(),
+            key: key.to_string(),
+        })
+    }
+}
+
+/// A revalidation in progress, see `Revalidations::start`.
+#[derive(Debug)]
+pub(crate) struct Revalidation {
+    revalidations: Arc<Revalidations>,
+    key: String,
+}
+
+impl Drop for Revalidation {
+    fn drop(&mut self) {
+        self.revalidations.pending.lock().unwrap().remove(&self.key);
+    }
+}
+
+/// 64-bit FNV-1a, a hash that stays the same across builds and platforms,
+/// unlike the std hashers, to name entry files.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use super::{DiskCache, Freshness, Revalidations};
+    use crate::{Endpoint, HttpResponse};
+
+    fn cache(name: &str) -> DiskCache {
+        let dir = std::env::temp_dir().join(format!(
+            "openweather-disk-cache-{}-{}",
+            name,
+            std::process::id()
+        ));
+        DiskCache::new(dir)
+    }
+
+    #[test]
+    fn entries_age_through_the_stale_windows() {
+        let cache = cache("age")
+            .stale_while_revalidate(Duration::from_secs(60))
+            .stale_if_error(Duration::from_secs(3600));
+        let fetched = SystemTime::now();
+        let response = HttpResponse {
+            status: 200,
+            headers: vec![],
+            body: b"{}".to_vec(),
+        };
+        cache.store_at("key", &response, fetched);
+
+        let freshness = |minutes: u64| {
+            let now = fetched + Duration::from_secs(minutes * 60);
+            cache
+                .load_at("key", Endpoint::Current, now)
+                .map(|stored| stored.freshness)
+        };
+        assert_eq!(freshness(9), Some(Freshness::Fresh));
+        assert_eq!(freshness(10), Some(Freshness::Revalidate));
+        assert_eq!(freshness(11), Some(Freshness::IfError));
+        assert_eq!(freshness(70), None);
+
+        let stored = cache.load_at("key", Endpoint::Current, fetched).unwrap();
+        assert_eq!(stored.response, response);
+        assert!(cache.load_at("other", Endpoint::Current, fetched).is_none());
+
+        let other = cache.dir().join("settings.json");
+        std::fs::write(&other, "{}").unwrap();
+        let leftover = cache.path("key").with_extension("1.0.tmp");
+        std::fs::write(&leftover, "{").unwrap();
+        cache.clear().unwrap();
+        assert!(!leftover.exists());
+        assert!(cache.load_at("key", Endpoint::Current, fetched).is_none());
+        assert!(other.exists());
+        std::fs::remove_file(other).unwrap();
+        std::fs::remove_dir(cache.dir()).unwrap();
+    }
+
+    #[test]
+    fn a_key_is_revalidated_once_at_a_time() {
+        let revalidations = Arc::new(Revalidations::default());
+        let first = revalidations.start("key").unwrap();
+        assert!(revalidations.start("key").is_none());
+        assert!(revalidations.start("other").is_some());
+        drop(first);
+        assert!(revalidations.start("key").is_some());
+    }
+
+    #[test]
+    fn concurrent_stores_never_show_a_partial_entry() {
+        let cache = cache("concurrent");
+        let response = HttpResponse {
+            status: 200,
+            headers: vec![],
+            body: vec![b'x'; 1024 * 1024],
+        };
+        cache.store("key", &response);
+        let writers: Vec<_> = (0..4)
+            .map(|_| {
+                let (cache, response) = (cache.clone(), response.clone());
+                std::thread::spawn(move || {
+                    for _ in 0..25 {
+                        cache.store("key", &response);
+                    }
+                })
+            })
+            .collect();
+        while writers.iter().any(|writer| !writer.is_finished()) {
+            let stored = cache.load_at("key", Endpoint::Current, SystemTime::now());
+            assert_eq!(stored.unwrap().response, response);
+        }
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(std::fs::read_dir(cache.dir()).unwrap().count(), 1);
+
+        cache.clear().unwrap();
+        std::fs::remove_dir(cache.dir()).unwrap();
+    }
+}
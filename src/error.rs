@@ -0,0 +1,137 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::{Error, ErrorReport};
+
+/// The broad class of an `Error`, see `Error::kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// 401, the key is invalid, blocked or lacks the subscription
+    InvalidKey,
+    /// 404, e.g. an unknown city
+    NotFound,
+    /// 429, the key exceeded its plan's quota, or the client's own
+    /// `RateLimit` was reached
+    RateLimited,
+    /// 5xx
+    Server,
+    /// Any other status the API reported as an error
+    Api,
+    /// A body that is neither the expected report nor an error report
+    Malformed,
+    /// The request never got a response
+    Connection,
+    /// Rejected before sending, e.g. an out of range argument
+    Input,
+}
+
+impl ErrorKind {
+    fn from_status(status: u16) -> ErrorKind {
+        match status {
+            401 => ErrorKind::InvalidKey,
+            404 => ErrorKind::NotFound,
+            429 => ErrorKind::RateLimited,
+            500..=599 => ErrorKind::Server,
+            _ => ErrorKind::Api,
+        }
+    }
+}
+
+/// An error answer of the API.
+#[derive(Debug)]
+pub struct ApiError {
+    /// The HTTP status, or the `cod` of the error report when the API sent
+    /// one along with a success status
+    pub status: u16,
+    /// The parsed error body, `None` when the body was not an error report
+    pub report: Option<ErrorReport>,
+    /// The raw response body
+    pub body: String,
+    /// How long the API asked to wait before trying again, from the
+    /// `Retry-After` header
+    pub retry_after: Option<Duration>,
+}
+
+impl ApiError {
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::from_status(self.status)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.report {
+            Some(report) => write!(f, "status {}, {}", self.status, report),
+            None => write!(f, "status {}, body: {}", self.status, self.body),
+        }
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Api(api) => api.kind(),
+            Error::RateLimited { .. } => ErrorKind::RateLimited,
+            Error::KeysUnavailable { rejected: true, .. } => ErrorKind::InvalidKey,
+            Error::KeysUnavailable {
+                rejected: false, ..
+            } => ErrorKind::RateLimited,
+            Error::Coalesced(err) => err.kind(),
+            Error::Parsing(_) | Error::MalformedResponse { .. } => ErrorKind::Malformed,
+            #[cfg(feature = "blocking")]
+            Error::Connection(_) => ErrorKind::Connection,
+            #[cfg(feature = "async")]
+            Error::Reqwest(_) => ErrorKind::Connection,
+            Error::Transport(_) => ErrorKind::Connection,
+            Error::Input { .. } | Error::UrlParsing(_) => ErrorKind::Input,
+        }
+    }
+
+    /// Whether sending the same request again may succeed: rate limiting by
+    /// the API, server errors and failed connections. The client's own
+    /// `RateLimit` is not retried, its budget being spent until the window
+    /// resets, and neither are keys the `KeyPool` left out.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::RateLimited { .. } | Error::KeysUnavailable { .. } => false,
+            Error::Coalesced(err) => err.is_retryable(),
+            _ => matches!(
+                self.kind(),
+                ErrorKind::RateLimited | ErrorKind::Server | ErrorKind::Connection
+            ),
+        }
+    }
+
+    /// The HTTP status of the response the error was made from.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            Error::Api(api) => Some(api.status),
+            Error::MalformedResponse { status, .. } => Some(*status),
+            Error::Coalesced(err) => err.status(),
+            _ => None,
+        }
+    }
+
+    /// How long the API asked to wait before sending the request again.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Error::Api(api) => api.retry_after,
+            Error::RateLimited { retry_after } | Error::KeysUnavailable { retry_after, .. } => {
+                Some(*retry_after)
+            }
+            Error::Coalesced(err) => err.retry_after(),
+            _ => None,
+        }
+    }
+
+    /// The raw body of the response the error was made from, for
+    /// diagnostics.
+    pub fn body(&self) -> Option<&str> {
+        match self {
+            Error::Api(api) => Some(&api.body),
+            Error::MalformedResponse { body, .. } => Some(body),
+            Error::Coalesced(err) => err.body(),
+            _ => None,
+        }
+    }
+}
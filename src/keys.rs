@@ -0,0 +1,342 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::request::Api;
+use crate::{Error, ErrorKind, Result};
+
+/// How a `KeyPool` picks the key of a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyStrategy {
+    /// Spreads requests evenly over the keys
+    RoundRobin,
+    /// Uses the first key until it is rejected (401) or rate limited (429),
+    /// then the next one
+    Failover,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PoolKey {
+    key: String,
+    api: Option<Api>,
+}
+
+/// Several API keys shared by a client, see `ClientBuilder::key_pool`.
+///
+/// Keys added with `key_for` are only used for requests to that API, e.g.
+/// for One Call 3.0, which needs a subscription of its own; requests to an
+/// API without keys of its own use the keys added with `key`. A key the API
+/// rejects with a 401 is quarantined, left out for `quarantine`, and with
+/// `KeyStrategy::Failover` a rate limited key is left out for as long as the
+/// API asks, a minute when it does not say. The request is then sent again
+/// with another key right away, and once every key is left out requests fail
+/// with `Error::KeysUnavailable`. A request to an API the pool has no key for,
+/// e.g. with only `key_for` keys of other APIs, fails with `Error::Input`.
+///
+/// ```
+/// use openweather::{Api, KeyPool, KeyStrategy};
+///
+/// let pool = KeyPool::new(KeyStrategy::Failover)
+///     .key("PRIMARY_KEY")
+///     .key("BACKUP_KEY")
+///     .key_for(Api::OneCall, "ONE_CALL_KEY");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPool {
+    keys: Vec<PoolKey>,
+    strategy: KeyStrategy,
+    quarantine: Duration,
+}
+
+impl KeyPool {
+    /// An empty pool, quarantining rejected keys for an hour.
+    pub fn new(strategy: KeyStrategy) -> KeyPool {
+        KeyPool {
+            keys: vec![],
+            strategy,
+            quarantine: Duration::from_secs(60 * 60),
+        }
+    }
+
+    /// Adds a key for every API without keys of its own.
+    pub fn key(mut self, key: &str) -> Self {
+        self.keys.push(PoolKey {
+            key: key.to_string(),
+            api: None,
+        });
+        self
+    }
+
+    /// Adds a key used only for requests to `api`.
+    pub fn key_for(mut self, api: Api, key: &str) -> Self {
+        self.keys.push(PoolKey {
+            key: key.to_string(),
+            api: Some(api),
+        });
+        self
+    }
+
+    /// How long a rejected key is left out.
+    pub fn quarantine(mut self, quarantine: Duration) -> Self {
+        self.quarantine = quarantine;
+        self
+    }
+}
+
+/// Counters of a key of a client, see `Client::key_stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyStats {
+    pub key: String,
+    /// The API the key is reserved for, `None` for the shared keys
+    pub api: Option<Api>,
+    /// Requests sent with the key
+    pub requests: u64,
+    /// Requests rejected with a 401
+    pub rejected: u64,
+    /// Requests rate limited with a 429
+    pub rate_limited: u64,
+    /// Whether the key is left out right now
+    pub quarantined: bool,
+}
+
+#[derive(Debug, Default)]
+struct KeyState {
+    requests: u64,
+    rejected: u64,
+    rate_limited: u64,
+    unusable_until: Option<Instant>,
+    /// Whether the key was last left out for being rejected
+    rejected_last: bool,
+}
+
+#[derive(Debug)]
+struct State {
+    keys: Vec<KeyState>,
+    next: usize,
+}
+
+/// The shared state of a client's keys.
+#[derive(Debug)]
+pub(crate) struct Keys {
+    pool: KeyPool,
+    state: Mutex<State>,
+}
+
+impl Keys {
+    pub fn new(pool: KeyPool) -> Keys {
+        let state = State {
+            keys: pool.keys.iter().map(|_| KeyState::default()).collect(),
+            next: 0,
+        };
+        Keys {
+            pool,
+            state: Mutex::new(state),
+        }
+    }
+
+    pub fn single(key: &str) -> Keys {
+        Keys::new(KeyPool::new(KeyStrategy::Failover).key(key))
+    }
+
+    /// The index and value of the key to send a request to `api` with.
+    pub fn pick(&self, api: Api) -> Result<(usize, String)> {
+        self.pick_at(api, Instant::now())
+    }
+
+    fn pick_at(&self, api: Api, now: Instant) -> Result<(usize, String)> {
+        let own = self.pool.keys.iter().any(|key| key.api == Some(api));
+        let scope = if own { Some(api) } else { None };
+        if !self.pool.keys.iter().any(|key| key.api == scope) {
+            let msg = if self.pool.keys.is_empty() {
+                format!("no API key for {:?}, the key pool is empty", api)
+            } else {
+                format!(
+                    "no API key for {:?}, the key pool only has keys for other APIs",
+                    api
+                )
+            };
+            return Err(Error::Input { msg });
+        }
+        let mut state = self.state.lock().unwrap();
+        let usable: Vec<usize> = (0..self.pool.keys.len())
+            .filter(|&i| self.pool.keys[i].api == scope)
+            .filter(|&i| {
+                state.keys[i]
+                    .unusable_until
+                    .is_none_or(|until| until <= now)
+            })
+            .collect();
+        let index = match (self.pool.strategy, usable.is_empty()) {
+            (_, true) => {
+                // Rate limited keys come back first, and a wait may do.
+                let left_out = (0..self.pool.keys.len())
+                    .filter(|&i| self.pool.keys[i].api == scope)
+                    .map(|i| &state.keys[i]);
+                let rejected = left_out.clone().all(|key| key.rejected_last);
+                let until = left_out.filter_map(|key| key.unusable_until).min();
+                return Err(Error::KeysUnavailable {
+                    api,
+                    rejected,
+                    retry_after: until.map_or(Duration::ZERO, |until| until - now),
+                });
+            }
+            (KeyStrategy::Failover, false) => usable[0],
+            (KeyStrategy::RoundRobin, false) => {
+                let index = usable[state.next % usable.len()];
+                state.next = state.next.wrapping_add(1);
+                index
+            }
+        };
+        state.keys[index].requests += 1;
+        Ok((index, self.pool.keys[index].key.clone()))
+    }
+
+    /// Records the failure of a request sent with the key at `index`,
+    /// returning whether the key was left out so another one may be tried.
+    /// The only key of an API is never left out.
+    pub fn report(&self, index: usize, err: &Error) -> bool {
+        self.report_at(index, err, Instant::now())
+    }
+
+    fn report_at(&self, index: usize, err: &Error, now: Instant) -> bool {
+        let scope = self.pool.keys[index].api;
+        let alone = self.pool.keys.iter().filter(|key| key.api == scope).count() == 1;
+        let mut state = self.state.lock().unwrap();
+        let key = &mut state.keys[index];
+        let left_out_for = match err.kind() {
+            ErrorKind::InvalidKey => {
+                key.rejected += 1;
+                self.pool.quarantine
+            }
+            ErrorKind::RateLimited if err.status() == Some(429) => {
+                key.rate_limited += 1;
+                if self.pool.strategy != KeyStrategy::Failover {
+                    return false;
+                }
+                err.retry_after().unwrap_or(Duration::from_secs(60))
+            }
+            _ => return false,
+        };
+        // Leaving out the only key would fail every request in its place.
+        if alone {
+            return false;
+        }
+        key.unusable_until = Some(now + left_out_for);
+        key.rejected_last = err.kind() == ErrorKind::InvalidKey;
+        true
+    }
+
+    /// How many keys a request to `api` can be sent with.
+    pub fn len(&self, api: Api) -> usize {
+        let own = self
+            .pool
+            .keys
+            .iter()
+            .filter(|key| key.api == Some(api))
+            .count();
+        if own > 0 {
+            own
+        } else {
+            self.pool
+                .keys
+                .iter()
+                .filter(|key| key.api.is_none())
+                .count()
+        }
+    }
+
+    pub fn stats(&self) -> Vec<KeyStats> {
+        let now = Instant::now();
+        let state = self.state.lock().unwrap();
+        self.pool
+            .keys
+            .iter()
+            .zip(&state.keys)
+            .map(|(key, state)| KeyStats {
+                key: key.key.clone(),
+                api: key.api,
+                requests: state.requests,
+                rejected: state.rejected,
+                rate_limited: state.rate_limited,
+                quarantined: state.unusable_until.is_some_and(|until| until > now),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::Keys;
+    use crate::request::Api;
+    use crate::{ApiError, Error, ErrorKind, KeyPool, KeyStrategy};
+
+    fn status(status: u16) -> Error {
+        Error::Api(ApiError {
+            status,
+            report: None,
+            body: String::new(),
+            retry_after: None,
+        })
+    }
+
+    #[test]
+    fn rotates_over_the_keys_of_the_api() {
+        let keys = Keys::new(
+            KeyPool::new(KeyStrategy::RoundRobin)
+                .key("a")
+                .key("b")
+                .key_for(Api::OneCall, "c"),
+        );
+        let now = Instant::now();
+        let picks: Vec<_> = (0..4)
+            .map(|_| keys.pick_at(Api::Data, now).unwrap().1)
+            .collect();
+        assert_eq!(picks, vec!["a", "b", "a", "b"]);
+        assert_eq!(keys.pick_at(Api::OneCall, now).unwrap().1, "c");
+        assert!(!keys.report_at(0, &status(429), now));
+        assert_eq!(keys.stats()[0].rate_limited, 1);
+    }
+
+    #[test]
+    fn fails_over_and_quarantines_rejected_keys() {
+        let keys = Keys::new(
+            KeyPool::new(KeyStrategy::Failover)
+                .key("a")
+                .key("b")
+                .quarantine(Duration::from_secs(60)),
+        );
+        let now = Instant::now();
+        assert_eq!(keys.pick_at(Api::Data, now).unwrap(), (0, "a".to_string()));
+        assert!(keys.report_at(0, &status(401), now));
+        assert_eq!(keys.pick_at(Api::Data, now).unwrap().1, "b");
+        assert!(keys.report_at(1, &status(429), now));
+        let err = keys.pick_at(Api::Data, now).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::RateLimited);
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(60)));
+        assert!(!err.is_retryable());
+        let later = now + Duration::from_secs(60);
+        assert_eq!(keys.pick_at(Api::Data, later).unwrap().1, "a");
+
+        let stats = keys.stats();
+        assert_eq!((stats[0].requests, stats[0].rejected), (2, 1));
+        assert!(stats[0].quarantined);
+        assert!(!keys.report_at(1, &status(404), later));
+    }
+
+    #[test]
+    fn fails_without_keys_for_the_api() {
+        let keys = Keys::new(KeyPool::new(KeyStrategy::Failover).key_for(Api::OneCall, "a"));
+        let now = Instant::now();
+        assert_eq!(keys.pick_at(Api::OneCall, now).unwrap().1, "a");
+        for api in [Api::Data, Api::Geo] {
+            let err = keys.pick_at(api, now).unwrap_err();
+            assert!(matches!(err, Error::Input { .. }), "{:?}", err);
+            assert!(err.to_string().contains(&format!("{:?}", api)));
+        }
+
+        let empty = Keys::new(KeyPool::new(KeyStrategy::RoundRobin));
+        let err = empty.pick_at(Api::Data, now).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Input);
+    }
+}
@@ -1,36 +1,103 @@
 #![forbid(unsafe_code)]
 
-use serde;
-use serde_json;
-
-use time;
-use url;
-
+#[cfg(feature = "async")]
+mod async_client;
+#[cfg(feature = "blocking")]
+mod blocking_client;
+mod cache;
+#[cfg(feature = "city-list")]
+mod city_list;
+mod client;
+mod coalesce;
+mod condition;
+mod convert;
+mod disk_cache;
+mod error;
+mod keys;
 mod location;
+mod mock;
+mod one_call;
 mod parameters;
+mod pipeline;
+mod rate_limit;
+mod request;
+mod retry;
+mod timestamp;
+mod transport;
+mod units;
 mod weather_types;
 
-pub use location::{LocationSpecifier};
+#[cfg(feature = "async")]
+pub use async_client::AsyncClient;
+#[cfg(feature = "blocking")]
+pub use blocking_client::Client;
+pub use cache::{Cache, CacheStats, Endpoint};
+#[cfg(feature = "city-list")]
+pub use city_list::{City, CityList};
+pub use client::ClientBuilder;
+pub use condition::{ConditionCode, ConditionGroup, IconSize, Intensity};
+pub use convert::ConvertUnit;
+pub use disk_cache::DiskCache;
+pub use error::{ApiError, ErrorKind};
+pub use keys::{KeyPool, KeyStats, KeyStrategy};
+pub use location::LocationSpecifier;
+pub use mock::MockTransport;
+pub use one_call::{OneCall, OneCallBlock};
 pub use parameters::{Language, Settings, Unit};
-
-use log::debug;
-use url::Url;
+pub use rate_limit::{RateLimit, RateLimitMode, RateLimitStats};
+pub use request::Api;
+pub use retry::RetryPolicy;
+pub use timestamp::Timestamp;
+#[cfg(feature = "blocking")]
+pub use transport::HttpReqTransport;
+#[cfg(feature = "async")]
+pub use transport::ReqwestTransport;
+pub use transport::{AsyncTransport, BoxFuture, HttpResponse, Transport};
+pub use units::{
+    Length, LengthUnit, Measured, Pressure, PressureUnit, Speed, SpeedUnit, Temperature,
+    TemperatureScale,
+};
+
+pub use url::Url;
 pub use weather_types::*;
 
-static API_BASE: &str = "https://api.openweathermap.org/data/2.5/";
-
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Openweather API error: {0}")]
-    Api(ErrorReport),
+    Api(ApiError),
     #[error("Error parsing to json: {0}")]
     Parsing(#[from] serde_json::Error),
-    #[error("Error parsing to json. Parsing as Weather: {0} - Parsing as ErrorReport: {1}")]
-    Parsing2(serde_json::Error, serde_json::Error),
+    #[error("Malformed response with status {status}: {source}")]
+    MalformedResponse {
+        status: u16,
+        source: serde_json::Error,
+        body: String,
+    },
+    #[cfg(feature = "blocking")]
     #[error("Http-Req error: {0}")]
     Connection(#[from] http_req::error::Error),
+    #[cfg(feature = "async")]
+    #[error("Reqwest error: {0}")]
+    Reqwest(#[from] reqwest::Error),
+    #[error("Transport error: {0}")]
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    #[error("Client side rate limit reached, next call available in {retry_after:?}")]
+    RateLimited { retry_after: std::time::Duration },
+    /// The error of a request shared with concurrent identical requests,
+    /// see `ClientBuilder::coalesce_requests`
+    #[error("{0}")]
+    Coalesced(std::sync::Arc<Error>),
+    /// Every key a request could be sent with is left out, see `KeyPool`
+    #[error("No usable API key for {api:?}, the next one is available in {retry_after:?}")]
+    KeysUnavailable {
+        api: Api,
+        /// Whether the keys were left out for being rejected (401) rather
+        /// than rate limited (429)
+        rejected: bool,
+        retry_after: std::time::Duration,
+    },
     #[error("Bad input: {msg}")]
     Input { msg: String },
     #[error("Error parsing url: {0}")]
@@ -40,247 +107,124 @@ pub enum Error {
 /// A specialized Result type for prometheus.
 pub type Result<T> = core::result::Result<T, Error>;
 
-fn get<T>(url: &str) -> Result<T>
-where
-    T: serde::de::DeserializeOwned,
-{
-    let mut res = Vec::new();
-
-    let status = http_req::request::get(url, &mut res)?;
-    debug!("Url: {:?}", url);
-    debug!("Status: {:?}", status);
-    let res = String::from_utf8_lossy(&res);
-    debug!("Body_String: {}", res);
-
-    match serde_json::from_str(&res) {
-        Ok(val) => Ok(val),
-        Err(e_weather) => {
-            let err_report: ErrorReport = serde_json::from_str(&res)
-                .map_err(|e_report| Error::Parsing2(e_report, e_weather))?;
-            Err(Error::Api(err_report))
-        }
-    }
+#[cfg(feature = "blocking")]
+fn client(key: &str, settings: &Settings) -> Client {
+    Client::builder(key).settings(settings.clone()).build()
 }
 
+#[cfg(feature = "blocking")]
 pub fn get_current_weather(
     location: &LocationSpecifier,
     key: &str,
     settings: &Settings,
 ) -> Result<WeatherReportCurrent> {
-    let mut base = String::from(API_BASE);
-    let mut params = location.format();
-
-    base.push_str("weather");
-    params.push(("APPID".to_string(), key.to_string()));
-    params.append(&mut settings.format());
-
-    let url = Url::parse_with_params(&base, params)?;
-    get(&url.as_str())
+    client(key, settings).get_current_weather(location)
 }
 
+#[cfg(feature = "blocking")]
 pub fn get_5_day_forecast(
     location: &LocationSpecifier,
     key: &str,
     settings: &Settings,
 ) -> Result<WeatherReport5Day> {
-    let mut base = String::from(API_BASE);
-    let mut params = location.format();
-
-    base.push_str("forecast");
-    params.push(("APPID".to_string(), key.to_string()));
-    params.append(&mut settings.format());
-
-    let url = Url::parse_with_params(&base, params)?;
-    get(&url.as_str())
+    client(key, settings).get_5_day_forecast(location)
 }
 
+#[cfg(feature = "blocking")]
 pub fn get_16_day_forecast(
     location: &LocationSpecifier,
     key: &str,
     len: u8,
     settings: &Settings,
 ) -> Result<WeatherReport16Day> {
-    if len > 16 || len == 0 {
-        return Err(Error::Input {
-            msg: format!("Only support 1 to 16 day forecasts but {:?} requested", len),
-        });
-    }
-    let mut base = String::from(API_BASE);
-    let mut params = location.format();
-
-    base.push_str("forecast/daily");
-    params.push(("cnt".to_string(), format!("{}", len)));
-    params.push(("APPID".to_string(), key.to_string()));
-    params.append(&mut settings.format());
-
-    let url = Url::parse_with_params(&base, params)?;
-    get(&url.as_str())
+    client(key, settings).get_16_day_forecast(location, len)
 }
 
+#[cfg(feature = "blocking")]
 pub fn get_one_call_current(
     coordinates: &Coordinates,
     key: &str,
-    settings: &Settings
+    settings: &Settings,
 ) -> Result<WeatherReportOneCall> {
-    let mut base = String::from(API_BASE);
-    let mut params = settings.format();
-
-    base.push_str("onecall");
-    params.push(("lat".to_string(), format!("{}", coordinates.lat)));
-    params.push(("lon".to_string(), format!("{}", coordinates.lon)));
-    params.push(("exclude".to_string(), "minutely,hourly".to_string()));
-    params.push(("APPID".to_string(), key.to_string()));
-
-    let url = Url::parse_with_params(&base, params)?;
-    get(&url.as_str())
+    client(key, settings).get_one_call_current(coordinates)
 }
 
+#[cfg(feature = "blocking")]
 pub fn get_one_call_historical(
     coordinates: &Coordinates,
-    dt: u64,
+    dt: impl Into<Timestamp>,
     key: &str,
-    settings: &Settings
+    settings: &Settings,
 ) -> Result<WeatherReportOneCallHistorical> {
-    let mut base = String::from(API_BASE);
-    let mut params = settings.format();
-
-    base.push_str("onecall/timemachine");
-    params.push(("lat".to_string(), format!("{}", coordinates.lat)));
-    params.push(("lon".to_string(), format!("{}", coordinates.lon)));
-    params.push(("dt".to_string(), format!("{}", dt)));
-    params.push(("APPID".to_string(), key.to_string()));
-
-    let url = Url::parse_with_params(&base, params)?;
-    get(&url.as_str())
+    client(key, settings).get_one_call_historical(coordinates, dt.into())
 }
 
+#[cfg(feature = "blocking")]
 pub fn get_historical_data(
     location: &LocationSpecifier,
     key: &str,
-    start: time::Timespec,
-    end: time::Timespec,
+    start: impl Into<Timestamp>,
+    end: impl Into<Timestamp>,
     settings: &Settings,
 ) -> Result<WeatherReportHistorical> {
-    let mut base = String::from(API_BASE);
-    let mut params = location.format();
-
-    base.push_str("history/city");
-    params.push(("type".to_string(), "hour".to_string()));
-    params.push(("start".to_string(), format!("{}", start.sec)));
-    params.push(("end".to_string(), format!("{}", end.sec)));
-    params.push(("APPID".to_string(), key.to_string()));
-    params.append(&mut settings.format());
-
-    let url = Url::parse_with_params(&base, params)?;
-    get(&url.as_str())
+    client(key, settings).get_historical_data(location, start, end)
 }
 
+#[cfg(feature = "blocking")]
 pub fn get_accumulated_temperature_data(
     location: &LocationSpecifier,
     key: &str,
-    start: time::Timespec,
-    end: time::Timespec,
+    start: impl Into<Timestamp>,
+    end: impl Into<Timestamp>,
     threshold: u32,
     settings: &Settings,
 ) -> Result<WeatherAccumulatedTemperature> {
-    let mut base = String::from(API_BASE);
-    let mut params = location.format();
-
-    base.push_str("history/accumulated_temperature");
-    params.push(("type".to_string(), "hour".to_string()));
-    params.push(("start".to_string(), format!("{}", start.sec)));
-    params.push(("end".to_string(), format!("{}", end.sec)));
-    params.push(("threshold".to_string(), format!("{}", threshold)));
-    params.push(("APPID".to_string(), key.to_string()));
-    params.append(&mut settings.format());
-
-    let url = Url::parse_with_params(&base, params)?;
-    get(&url.as_str())
+    client(key, settings).get_accumulated_temperature_data(location, start, end, threshold)
 }
 
+#[cfg(feature = "blocking")]
 pub fn get_accumulated_precipitation_data(
     location: &LocationSpecifier,
     key: &str,
-    start: time::Timespec,
-    end: time::Timespec,
+    start: impl Into<Timestamp>,
+    end: impl Into<Timestamp>,
     threshold: u32,
     settings: &Settings,
 ) -> Result<WeatherAccumulatedPrecipitation> {
-    let mut base = String::from(API_BASE);
-    let mut params = location.format();
-
-    base.push_str("history/accumulated_precipitation");
-    params.push(("type".to_string(), "hour".to_string()));
-    params.push(("start".to_string(), format!("{}", start.sec)));
-    params.push(("end".to_string(), format!("{}", end.sec)));
-    params.push(("threshold".to_string(), format!("{}", threshold)));
-    params.push(("APPID".to_string(), key.to_string()));
-    params.append(&mut settings.format());
-
-    let url = Url::parse_with_params(&base, params)?;
-    get(&url.as_str())
+    client(key, settings).get_accumulated_precipitation_data(location, start, end, threshold)
 }
 
+#[cfg(feature = "blocking")]
 pub fn get_current_uv_index(
     location: &LocationSpecifier,
     key: &str,
     settings: &Settings,
 ) -> Result<UvIndex> {
-    let mut base = String::from(API_BASE);
-    let mut params = location.format();
-
-    base.push_str("uvi");
-    params.push(("APPID".to_string(), key.to_string()));
-    params.append(&mut settings.format());
-
-    let url = Url::parse_with_params(&base, params)?;
-    get(&url.as_str())
+    client(key, settings).get_current_uv_index(location)
 }
 
+#[cfg(feature = "blocking")]
 pub fn get_forecast_uv_index(
     location: &LocationSpecifier,
     key: &str,
     len: u8,
     settings: &Settings,
 ) -> Result<ForecastUvIndex> {
-    if len > 8 || len == 0 {
-        return Err(Error::Input {
-            msg: format!("Only support 1 to 8 day forecasts but {:?} requested", len),
-        });
-    }
-    let mut base = String::from(API_BASE);
-    let mut params = location.format();
-
-    base.push_str("uvi/forecast");
-    params.push(("cnt".to_string(), format!("{}", len)));
-    params.push(("APPID".to_string(), key.to_string()));
-    params.append(&mut settings.format());
-
-    let url = Url::parse_with_params(&base, params)?;
-    get(&url.as_str())
+    client(key, settings).get_forecast_uv_index(location, len)
 }
 
+#[cfg(feature = "blocking")]
 pub fn get_historical_uv_index(
     location: &LocationSpecifier,
     key: &str,
-    start: time::Timespec,
-    end: time::Timespec,
+    start: impl Into<Timestamp>,
+    end: impl Into<Timestamp>,
     settings: &Settings,
 ) -> Result<HistoricalUvIndex> {
-    let mut base = String::from(API_BASE);
-    let mut params = location.format();
-
-    base.push_str("uvi/history");
-    params.push(("start".to_string(), format!("{}", start.sec)));
-    params.push(("end".to_string(), format!("{}", end.sec)));
-    params.push(("APPID".to_string(), key.to_string()));
-    params.append(&mut settings.format());
-
-    let url = Url::parse_with_params(&base, params)?;
-    get(&url.as_str())
+    client(key, settings).get_historical_uv_index(location, start, end)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "blocking"))]
 mod tests {
     use crate::{Coordinates, LocationSpecifier, Settings};
     static SETTINGS: &Settings = &Settings {
@@ -288,13 +232,13 @@ mod tests {
         lang: None,
     };
 
-    use dotenv;
     fn api_key() -> String {
         let key = "API_KEY";
         dotenv::var(key).expect("get api key for testing from .env file")
     }
 
     #[test]
+    #[ignore = "requires API_KEY in .env and network access"]
     fn get_current_weather() {
         let loc = LocationSpecifier::CityAndCountryName {
             city: "Minneapolis".into(),
@@ -306,6 +250,7 @@ mod tests {
     }
 
     #[test]
+    #[ignore = "requires API_KEY in .env and network access"]
     fn get_5_day_forecast() {
         let loc = LocationSpecifier::CityAndCountryName {
             city: "Minneapolis".into(),
@@ -317,10 +262,11 @@ mod tests {
     }
 
     #[test]
+    #[ignore = "requires API_KEY in .env and network access"]
     fn get_one_call_current() {
         let coordinates = Coordinates {
-            lat: 37.650470,
-            lon: -119.037439,
+            lat: 37.65047,
+            lon: -119.03744,
         };
         let weather = crate::get_one_call_current(&coordinates, &api_key(), SETTINGS)
             .expect("failure getting one-call current weather");
@@ -328,12 +274,13 @@ mod tests {
     }
 
     #[test]
+    #[ignore = "requires API_KEY in .env and network access"]
     fn get_one_call_historical() {
         let coordinates = Coordinates {
             lat: 40.457177,
-            lon: -106.804447,
+            lon: -106.80444,
         };
-        let dt = (time::now_utc() - time::Duration::days(1)).to_timespec().sec as u64;
+        let dt = time::OffsetDateTime::now_utc() - time::Duration::days(1);
         let weather = crate::get_one_call_historical(&coordinates, dt, &api_key(), SETTINGS)
             .expect("failure getting one-call current weather");
         println!("current weather in Steamboat Springs, CO: {:?}", weather);
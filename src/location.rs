@@ -1,4 +1,286 @@
-#[derive(Debug)]
+use std::convert::TryInto;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use serde::de::{Deserializer, Error as _};
+use serde::ser::Serializer;
+
+use crate::{Error, Result};
+
+/// The most city IDs a `group` request takes.
+pub(crate) const GROUP_LIMIT: usize = 20;
+/// The most cities a `find` request returns.
+pub(crate) const FIND_LIMIT: u16 = 50;
+
+/// The ISO 3166-1 alpha-2 and alpha-3 country codes by alpha-2 code, plus
+/// `XK`/`XKX` which OpenWeatherMap uses for Kosovo.
+static COUNTRY_CODES: [(&str, &str); 250] = [
+    ("AD", "AND"),
+    ("AE", "ARE"),
+    ("AF", "AFG"),
+    ("AG", "ATG"),
+    ("AI", "AIA"),
+    ("AL", "ALB"),
+    ("AM", "ARM"),
+    ("AO", "AGO"),
+    ("AQ", "ATA"),
+    ("AR", "ARG"),
+    ("AS", "ASM"),
+    ("AT", "AUT"),
+    ("AU", "AUS"),
+    ("AW", "ABW"),
+    ("AX", "ALA"),
+    ("AZ", "AZE"),
+    ("BA", "BIH"),
+    ("BB", "BRB"),
+    ("BD", "BGD"),
+    ("BE", "BEL"),
+    ("BF", "BFA"),
+    ("BG", "BGR"),
+    ("BH", "BHR"),
+    ("BI", "BDI"),
+    ("BJ", "BEN"),
+    ("BL", "BLM"),
+    ("BM", "BMU"),
+    ("BN", "BRN"),
+    ("BO", "BOL"),
+    ("BQ", "BES"),
+    ("BR", "BRA"),
+    ("BS", "BHS"),
+    ("BT", "BTN"),
+    ("BV", "BVT"),
+    ("BW", "BWA"),
+    ("BY", "BLR"),
+    ("BZ", "BLZ"),
+    ("CA", "CAN"),
+    ("CC", "CCK"),
+    ("CD", "COD"),
+    ("CF", "CAF"),
+    ("CG", "COG"),
+    ("CH", "CHE"),
+    ("CI", "CIV"),
+    ("CK", "COK"),
+    ("CL", "CHL"),
+    ("CM", "CMR"),
+    ("CN", "CHN"),
+    ("CO", "COL"),
+    ("CR", "CRI"),
+    ("CU", "CUB"),
+    ("CV", "CPV"),
+    ("CW", "CUW"),
+    ("CX", "CXR"),
+    ("CY", "CYP"),
+    ("CZ", "CZE"),
+    ("DE", "DEU"),
+    ("DJ", "DJI"),
+    ("DK", "DNK"),
+    ("DM", "DMA"),
+    ("DO", "DOM"),
+    ("DZ", "DZA"),
+    ("EC", "ECU"),
+    ("EE", "EST"),
+    ("EG", "EGY"),
+    ("EH", "ESH"),
+    ("ER", "ERI"),
+    ("ES", "ESP"),
+    ("ET", "ETH"),
+    ("FI", "FIN"),
+    ("FJ", "FJI"),
+    ("FK", "FLK"),
+    ("FM", "FSM"),
+    ("FO", "FRO"),
+    ("FR", "FRA"),
+    ("GA", "GAB"),
+    ("GB", "GBR"),
+    ("GD", "GRD"),
+    ("GE", "GEO"),
+    ("GF", "GUF"),
+    ("GG", "GGY"),
+    ("GH", "GHA"),
+    ("GI", "GIB"),
+    ("GL", "GRL"),
+    ("GM", "GMB"),
+    ("GN", "GIN"),
+    ("GP", "GLP"),
+    ("GQ", "GNQ"),
+    ("GR", "GRC"),
+    ("GS", "SGS"),
+    ("GT", "GTM"),
+    ("GU", "GUM"),
+    ("GW", "GNB"),
+    ("GY", "GUY"),
+    ("HK", "HKG"),
+    ("HM", "HMD"),
+    ("HN", "HND"),
+    ("HR", "HRV"),
+    ("HT", "HTI"),
+    ("HU", "HUN"),
+    ("ID", "IDN"),
+    ("IE", "IRL"),
+    ("IL", "ISR"),
+    ("IM", "IMN"),
+    ("IN", "IND"),
+    ("IO", "IOT"),
+    ("IQ", "IRQ"),
+    ("IR", "IRN"),
+    ("IS", "ISL"),
+    ("IT", "ITA"),
+    ("JE", "JEY"),
+    ("JM", "JAM"),
+    ("JO", "JOR"),
+    ("JP", "JPN"),
+    ("KE", "KEN"),
+    ("KG", "KGZ"),
+    ("KH", "KHM"),
+    ("KI", "KIR"),
+    ("KM", "COM"),
+    ("KN", "KNA"),
+    ("KP", "PRK"),
+    ("KR", "KOR"),
+    ("KW", "KWT"),
+    ("KY", "CYM"),
+    ("KZ", "KAZ"),
+    ("LA", "LAO"),
+    ("LB", "LBN"),
+    ("LC", "LCA"),
+    ("LI", "LIE"),
+    ("LK", "LKA"),
+    ("LR", "LBR"),
+    ("LS", "LSO"),
+    ("LT", "LTU"),
+    ("LU", "LUX"),
+    ("LV", "LVA"),
+    ("LY", "LBY"),
+    ("MA", "MAR"),
+    ("MC", "MCO"),
+    ("MD", "MDA"),
+    ("ME", "MNE"),
+    ("MF", "MAF"),
+    ("MG", "MDG"),
+    ("MH", "MHL"),
+    ("MK", "MKD"),
+    ("ML", "MLI"),
+    ("MM", "MMR"),
+    ("MN", "MNG"),
+    ("MO", "MAC"),
+    ("MP", "MNP"),
+    ("MQ", "MTQ"),
+    ("MR", "MRT"),
+    ("MS", "MSR"),
+    ("MT", "MLT"),
+    ("MU", "MUS"),
+    ("MV", "MDV"),
+    ("MW", "MWI"),
+    ("MX", "MEX"),
+    ("MY", "MYS"),
+    ("MZ", "MOZ"),
+    ("NA", "NAM"),
+    ("NC", "NCL"),
+    ("NE", "NER"),
+    ("NF", "NFK"),
+    ("NG", "NGA"),
+    ("NI", "NIC"),
+    ("NL", "NLD"),
+    ("NO", "NOR"),
+    ("NP", "NPL"),
+    ("NR", "NRU"),
+    ("NU", "NIU"),
+    ("NZ", "NZL"),
+    ("OM", "OMN"),
+    ("PA", "PAN"),
+    ("PE", "PER"),
+    ("PF", "PYF"),
+    ("PG", "PNG"),
+    ("PH", "PHL"),
+    ("PK", "PAK"),
+    ("PL", "POL"),
+    ("PM", "SPM"),
+    ("PN", "PCN"),
+    ("PR", "PRI"),
+    ("PS", "PSE"),
+    ("PT", "PRT"),
+    ("PW", "PLW"),
+    ("PY", "PRY"),
+    ("QA", "QAT"),
+    ("RE", "REU"),
+    ("RO", "ROU"),
+    ("RS", "SRB"),
+    ("RU", "RUS"),
+    ("RW", "RWA"),
+    ("SA", "SAU"),
+    ("SB", "SLB"),
+    ("SC", "SYC"),
+    ("SD", "SDN"),
+    ("SE", "SWE"),
+    ("SG", "SGP"),
+    ("SH", "SHN"),
+    ("SI", "SVN"),
+    ("SJ", "SJM"),
+    ("SK", "SVK"),
+    ("SL", "SLE"),
+    ("SM", "SMR"),
+    ("SN", "SEN"),
+    ("SO", "SOM"),
+    ("SR", "SUR"),
+    ("SS", "SSD"),
+    ("ST", "STP"),
+    ("SV", "SLV"),
+    ("SX", "SXM"),
+    ("SY", "SYR"),
+    ("SZ", "SWZ"),
+    ("TC", "TCA"),
+    ("TD", "TCD"),
+    ("TF", "ATF"),
+    ("TG", "TGO"),
+    ("TH", "THA"),
+    ("TJ", "TJK"),
+    ("TK", "TKL"),
+    ("TL", "TLS"),
+    ("TM", "TKM"),
+    ("TN", "TUN"),
+    ("TO", "TON"),
+    ("TR", "TUR"),
+    ("TT", "TTO"),
+    ("TV", "TUV"),
+    ("TW", "TWN"),
+    ("TZ", "TZA"),
+    ("UA", "UKR"),
+    ("UG", "UGA"),
+    ("UM", "UMI"),
+    ("US", "USA"),
+    ("UY", "URY"),
+    ("UZ", "UZB"),
+    ("VA", "VAT"),
+    ("VC", "VCT"),
+    ("VE", "VEN"),
+    ("VG", "VGB"),
+    ("VI", "VIR"),
+    ("VN", "VNM"),
+    ("VU", "VUT"),
+    ("WF", "WLF"),
+    ("WS", "WSM"),
+    ("XK", "XKX"),
+    ("YE", "YEM"),
+    ("YT", "MYT"),
+    ("ZA", "ZAF"),
+    ("ZM", "ZMB"),
+    ("ZW", "ZWE"),
+];
+
+/// Where to get the weather for, turned into the query parameters of a
+/// request by `format`.
+///
+/// The variants can be built directly, in which case they are checked when a
+/// request is made, or with the constructors, which check them right away.
+/// Either way an invalid location fails with `Error::Input` without a call
+/// to the API.
+///
+/// Locations are equal when their fields are, coordinates comparing by value
+/// except that every NaN is equal to every other, which makes locations fit
+/// for hash map keys. They are serialized as the text of `Display`, e.g.
+/// `"zip:55401,US"`, and deserialized with `FromStr`, which checks them.
+#[derive(Debug, Clone)]
 pub enum LocationSpecifier {
     CityAndCountryName {
         city: String,
@@ -15,6 +297,8 @@ pub enum LocationSpecifier {
     },
 
     // The following location specifiers are used to specify multiple cities or a region
+    /// The cities within a box, which cannot cross the antimeridian:
+    /// `lon_left` must be less than `lon_right`
     BoundingBox {
         lon_left: f32,
         lat_bottom: f32,
@@ -31,29 +315,161 @@ pub enum LocationSpecifier {
 }
 
 impl LocationSpecifier {
+    /// A city name with an optional country code, e.g. `("London", "GB")`;
+    /// an empty `country` leaves the choice to the API.
+    pub fn city(city: &str, country: &str) -> Result<LocationSpecifier> {
+        LocationSpecifier::CityAndCountryName {
+            city: city.trim().to_string(),
+            country: country.trim().to_ascii_uppercase(),
+        }
+        .validated()
+    }
+
+    pub fn city_id(id: &str) -> Result<LocationSpecifier> {
+        LocationSpecifier::CityId(id.trim().to_string()).validated()
+    }
+
+    pub fn coordinates(lat: f32, lon: f32) -> Result<LocationSpecifier> {
+        LocationSpecifier::Coordinates { lat, lon }.validated()
+    }
+
+    /// A zip or post code with an optional country code, the API assuming
+    /// `US` when `country` is empty.
+    pub fn zip_code(zip: &str, country: &str) -> Result<LocationSpecifier> {
+        LocationSpecifier::ZipCode {
+            zip: zip.trim().to_string(),
+            country: country.trim().to_ascii_uppercase(),
+        }
+        .validated()
+    }
+
+    /// The cities within a box, `lon_left` being less than `lon_right`. A
+    /// region across the antimeridian takes two boxes, one on each side.
+    pub fn bounding_box(
+        lon_left: f32,
+        lat_bottom: f32,
+        lon_right: f32,
+        lat_top: f32,
+        zoom: f32,
+    ) -> Result<LocationSpecifier> {
+        LocationSpecifier::BoundingBox {
+            lon_left,
+            lat_bottom,
+            lon_right,
+            lat_top,
+            zoom,
+        }
+        .validated()
+    }
+
+    /// Up to 50 cities around a point.
+    pub fn circle(lat: f32, lon: f32, count: u16) -> Result<LocationSpecifier> {
+        LocationSpecifier::Circle { lat, lon, count }.validated()
+    }
+
+    /// Up to 20 cities by their IDs.
+    pub fn city_ids<S: AsRef<str>>(ids: &[S]) -> Result<LocationSpecifier> {
+        LocationSpecifier::CityIds(
+            ids.iter()
+                .map(|id| id.as_ref().trim().to_string())
+                .collect(),
+        )
+        .validated()
+    }
+
+    fn validated(self) -> Result<LocationSpecifier> {
+        self.validate()?;
+        Ok(self)
+    }
+
+    /// Checks the location is one the API accepts: coordinates in range,
+    /// numeric city IDs, known country codes and counts within the limits
+    /// of the multi-city endpoints.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            LocationSpecifier::CityAndCountryName { city, country } => {
+                check_name("city", city)?;
+                check_country(country)
+            }
+            LocationSpecifier::CityId(id) => check_city_id(id),
+            LocationSpecifier::Coordinates { lat, lon } => check_coordinates(*lat, *lon),
+            LocationSpecifier::ZipCode { zip, country } => {
+                check_name("zip code", zip)?;
+                check_country(country)
+            }
+            LocationSpecifier::BoundingBox {
+                lon_left,
+                lat_bottom,
+                lon_right,
+                lat_top,
+                zoom,
+            } => {
+                check_coordinates(*lat_bottom, *lon_left)?;
+                check_coordinates(*lat_top, *lon_right)?;
+                if lat_bottom >= lat_top {
+                    return input(format!(
+                        "bounding box {},{},{},{} must have its bottom edge below its top edge",
+                        lon_left, lat_bottom, lon_right, lat_top
+                    ));
+                }
+                if lon_left >= lon_right {
+                    return input(format!(
+                        "bounding box {},{},{},{} must have its left edge west of its right edge, \
+                         a box across the antimeridian has to be split in two",
+                        lon_left, lat_bottom, lon_right, lat_top
+                    ));
+                }
+                if !zoom.is_finite() || *zoom < 0.0 {
+                    return input(format!("zoom {} must be zero or more", zoom));
+                }
+                Ok(())
+            }
+            LocationSpecifier::Circle { lat, lon, count } => {
+                check_coordinates(*lat, *lon)?;
+                if *count > FIND_LIMIT || *count == 0 {
+                    return input(format!(
+                        "Only support 1 to {} cities in a circle but {:?} requested",
+                        FIND_LIMIT, count
+                    ));
+                }
+                Ok(())
+            }
+            LocationSpecifier::CityIds(ids) => {
+                if ids.len() > GROUP_LIMIT || ids.is_empty() {
+                    return input(format!(
+                        "Only support 1 to {} city IDs but {:?} requested",
+                        GROUP_LIMIT,
+                        ids.len()
+                    ));
+                }
+                ids.iter().try_for_each(|id| check_city_id(id))
+            }
+        }
+    }
+
     pub fn format(&self) -> Vec<(String, String)> {
         match &self {
             LocationSpecifier::CityAndCountryName { city, country } => {
-                if *country == "" {
-                    return vec![("q".to_string(), city.to_string())];
+                if country.is_empty() {
+                    vec![("q".to_string(), city.to_string())]
                 } else {
-                    return vec![("q".to_string(), format!("{},{}", city, country))];
+                    vec![("q".to_string(), format!("{},{}", city, country))]
                 }
             }
             LocationSpecifier::CityId(id) => {
-                return vec![("id".to_string(), id.to_string())];
+                vec![("id".to_string(), id.to_string())]
             }
             LocationSpecifier::Coordinates { lat, lon } => {
-                return vec![
+                vec![
                     ("lat".to_string(), format!("{}", lat)),
                     ("lon".to_string(), format!("{}", lon)),
-                ];
+                ]
             }
             LocationSpecifier::ZipCode { zip, country } => {
-                if *country == "" {
-                    return vec![("zip".to_string(), zip.to_string())];
+                if country.is_empty() {
+                    vec![("zip".to_string(), zip.to_string())]
                 } else {
-                    return vec![("zip".to_string(), format!("{},{}", zip, country))];
+                    vec![("zip".to_string(), format!("{},{}", zip, country))]
                 }
             }
             LocationSpecifier::BoundingBox {
@@ -63,28 +479,501 @@ impl LocationSpecifier {
                 lat_top,
                 zoom,
             } => {
-                return vec![(
+                vec![(
                     "bbox".to_string(),
                     format!(
                         "{},{},{},{},{}",
                         lon_left, lat_bottom, lon_right, lat_top, zoom
                     ),
-                )];
+                )]
             }
             LocationSpecifier::Circle { lat, lon, count } => {
-                return vec![
+                vec![
                     ("lat".to_string(), format!("{}", lat)),
                     ("lon".to_string(), format!("{}", lon)),
                     ("cnt".to_string(), format!("{}", count)),
-                ];
+                ]
             }
             LocationSpecifier::CityIds(ids) => {
-                let mut locations: String = "".to_string();
-                for loc in ids {
-                    locations += loc;
+                vec![("id".to_string(), ids.join(","))]
+            }
+        }
+    }
+}
+
+impl PartialEq for LocationSpecifier {
+    fn eq(&self, other: &LocationSpecifier) -> bool {
+        use LocationSpecifier::*;
+        let same = |a: &f32, b: &f32| float_bits(*a) == float_bits(*b);
+        match (self, other) {
+            (
+                CityAndCountryName { city, country },
+                CityAndCountryName {
+                    city: other_city,
+                    country: other_country,
+                },
+            )
+            | (
+                ZipCode { zip: city, country },
+                ZipCode {
+                    zip: other_city,
+                    country: other_country,
+                },
+            ) => city == other_city && country == other_country,
+            (CityId(id), CityId(other_id)) => id == other_id,
+            (
+                Coordinates { lat, lon },
+                Coordinates {
+                    lat: lat2,
+                    lon: lon2,
+                },
+            ) => same(lat, lat2) && same(lon, lon2),
+            (
+                BoundingBox {
+                    lon_left,
+                    lat_bottom,
+                    lon_right,
+                    lat_top,
+                    zoom,
+                },
+                BoundingBox {
+                    lon_left: lon_left2,
+                    lat_bottom: lat_bottom2,
+                    lon_right: lon_right2,
+                    lat_top: lat_top2,
+                    zoom: zoom2,
+                },
+            ) => {
+                same(lon_left, lon_left2)
+                    && same(lat_bottom, lat_bottom2)
+                    && same(lon_right, lon_right2)
+                    && same(lat_top, lat_top2)
+                    && same(zoom, zoom2)
+            }
+            (
+                Circle { lat, lon, count },
+                Circle {
+                    lat: lat2,
+                    lon: lon2,
+                    count: count2,
+                },
+            ) => same(lat, lat2) && same(lon, lon2) && count == count2,
+            (CityIds(ids), CityIds(other_ids)) => ids == other_ids,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for LocationSpecifier {}
+
+impl Hash for LocationSpecifier {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            LocationSpecifier::CityAndCountryName { city, country }
+            | LocationSpecifier::ZipCode { zip: city, country } => {
+                city.hash(state);
+                country.hash(state);
+            }
+            LocationSpecifier::CityId(id) => id.hash(state),
+            LocationSpecifier::Coordinates { lat, lon } => {
+                float_bits(*lat).hash(state);
+                float_bits(*lon).hash(state);
+            }
+            LocationSpecifier::BoundingBox {
+                lon_left,
+                lat_bottom,
+                lon_right,
+                lat_top,
+                zoom,
+            } => {
+                for value in &[lon_left, lat_bottom, lon_right, lat_top, zoom] {
+                    float_bits(**value).hash(state);
                 }
-                return vec![("id".to_string(), locations.to_string())];
             }
+            LocationSpecifier::Circle { lat, lon, count } => {
+                float_bits(*lat).hash(state);
+                float_bits(*lon).hash(state);
+                count.hash(state);
+            }
+            LocationSpecifier::CityIds(ids) => ids.hash(state),
         }
     }
 }
+
+/// A coordinate as compared and hashed: `0.0` and `-0.0`, like every NaN,
+/// are the same.
+fn float_bits(value: f32) -> u32 {
+    if value == 0.0 {
+        0
+    } else if value.is_nan() {
+        f32::NAN.to_bits()
+    } else {
+        value.to_bits()
+    }
+}
+
+impl serde::Serialize for LocationSpecifier {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for LocationSpecifier {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+/// Writes the location in the form `FromStr` reads:
+///
+/// | Variant              | Text                                      |
+/// |----------------------|-------------------------------------------|
+/// | `CityAndCountryName` | `Minneapolis,US` or `Minneapolis`         |
+/// | `CityId`             | `id:5037649`                              |
+/// | `Coordinates`        | `44.98,-93.26`                            |
+/// | `ZipCode`            | `zip:55401,US` or `zip:55401`             |
+/// | `BoundingBox`        | `box:LON_LEFT,LAT_BOTTOM,LON_RIGHT,LAT_TOP,ZOOM` |
+/// | `Circle`             | `circle:LAT,LON,COUNT`                    |
+/// | `CityIds`            | `ids:524901,703448`                       |
+impl fmt::Display for LocationSpecifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocationSpecifier::CityAndCountryName { city, country } => {
+                if country.is_empty() {
+                    write!(f, "{}", city)
+                } else {
+                    write!(f, "{},{}", city, country)
+                }
+            }
+            LocationSpecifier::CityId(id) => write!(f, "id:{}", id),
+            LocationSpecifier::Coordinates { lat, lon } => write!(f, "{},{}", lat, lon),
+            LocationSpecifier::ZipCode { zip, country } => {
+                if country.is_empty() {
+                    write!(f, "zip:{}", zip)
+                } else {
+                    write!(f, "zip:{},{}", zip, country)
+                }
+            }
+            LocationSpecifier::BoundingBox {
+                lon_left,
+                lat_bottom,
+                lon_right,
+                lat_top,
+                zoom,
+            } => write!(
+                f,
+                "box:{},{},{},{},{}",
+                lon_left, lat_bottom, lon_right, lat_top, zoom
+            ),
+            LocationSpecifier::Circle { lat, lon, count } => {
+                write!(f, "circle:{},{},{}", lat, lon, count)
+            }
+            LocationSpecifier::CityIds(ids) => write!(f, "ids:{}", ids.join(",")),
+        }
+    }
+}
+
+/// Reads a location written by `Display`, or a `geo:` URI as described in
+/// RFC 5870, e.g. `geo:44.98,-93.26;u=35`. The altitude and the parameters
+/// of a URI are ignored, except a coordinate reference system other than
+/// WGS-84, which is rejected. The location is checked like the constructors
+/// do.
+///
+/// ```
+/// use openweather::LocationSpecifier;
+///
+/// let location: LocationSpecifier = "zip:55401,US".parse().unwrap();
+/// assert_eq!(location.to_string(), "zip:55401,US");
+/// ```
+impl FromStr for LocationSpecifier {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<LocationSpecifier> {
+        let text = text.trim();
+        let (scheme, rest) = text.split_once(':').unwrap_or(("", text));
+        match scheme.trim().to_ascii_lowercase().as_str() {
+            "id" => LocationSpecifier::city_id(rest),
+            "ids" => LocationSpecifier::city_ids(&rest.split(',').collect::<Vec<_>>()),
+            "zip" => {
+                let (zip, country) = rest.split_once(',').unwrap_or((rest, ""));
+                LocationSpecifier::zip_code(zip, country)
+            }
+            "geo" => parse_geo_uri(text, rest),
+            "box" => {
+                let [lon_left, lat_bottom, lon_right, lat_top, zoom] =
+                    parts(text, rest, "box:LON_LEFT,LAT_BOTTOM,LON_RIGHT,LAT_TOP,ZOOM")?;
+                LocationSpecifier::bounding_box(
+                    number(text, lon_left)?,
+                    number(text, lat_bottom)?,
+                    number(text, lon_right)?,
+                    number(text, lat_top)?,
+                    number(text, zoom)?,
+                )
+            }
+            "circle" => {
+                let [lat, lon, count] = parts(text, rest, "circle:LAT,LON,COUNT")?;
+                LocationSpecifier::circle(
+                    number(text, lat)?,
+                    number(text, lon)?,
+                    number(text, count)?,
+                )
+            }
+            // Anything else is a city, a colon being part of its name.
+            _ => {
+                let (first, second) = text.split_once(',').unwrap_or((text, ""));
+                match (first.trim().parse(), second.trim().parse()) {
+                    (Ok(lat), Ok(lon)) => LocationSpecifier::coordinates(lat, lon),
+                    _ => LocationSpecifier::city(first, second),
+                }
+            }
+        }
+    }
+}
+
+/// Reads the part of a `geo:` URI after the scheme, `LAT,LON[,ALT][;PARAMS]`.
+fn parse_geo_uri(text: &str, rest: &str) -> Result<LocationSpecifier> {
+    let mut params = rest.split(';');
+    let coordinates: Vec<&str> = params.next().unwrap_or_default().split(',').collect();
+    if coordinates.len() != 2 && coordinates.len() != 3 {
+        return input(format!(
+            "{:?} is not a geo URI such as \"geo:44.98,-93.26\"",
+            text
+        ));
+    }
+    for param in params {
+        let (name, value) = param.split_once('=').unwrap_or((param, ""));
+        if name.trim().eq_ignore_ascii_case("crs") && !value.trim().eq_ignore_ascii_case("wgs84") {
+            return input(format!(
+                "{:?} uses the coordinate reference system {:?}, only wgs84 is supported",
+                text, value
+            ));
+        }
+    }
+    LocationSpecifier::coordinates(number(text, coordinates[0])?, number(text, coordinates[1])?)
+}
+
+/// Splits the comma separated values after the scheme of `text`, which must
+/// be `N` of them as in `form`.
+fn parts<'a, const N: usize>(text: &str, rest: &'a str, form: &str) -> Result<[&'a str; N]> {
+    let values: Vec<&str> = rest.split(',').collect();
+    values
+        .try_into()
+        .or_else(|_| input(format!("{:?} does not have the form {}", text, form)))
+}
+
+fn number<T: FromStr>(text: &str, value: &str) -> Result<T> {
+    value.trim().parse().or_else(|_| {
+        input(format!(
+            "{:?} in {:?} is not a valid number",
+            value.trim(),
+            text
+        ))
+    })
+}
+
+fn input<T>(msg: String) -> Result<T> {
+    Err(Error::Input { msg })
+}
+
+fn check_name(what: &str, name: &str) -> Result<()> {
+    if name.trim().is_empty() {
+        return input(format!("{} must not be empty", what));
+    }
+    if name.contains(',') {
+        return input(format!("{} {:?} must not contain a comma", what, name));
+    }
+    Ok(())
+}
+
+/// An empty country is left out of the request.
+fn check_country(country: &str) -> Result<()> {
+    let known = match country.len() {
+        0 => true,
+        2 => COUNTRY_CODES
+            .binary_search_by_key(&country, |(alpha_2, _)| alpha_2)
+            .is_ok(),
+        3 => COUNTRY_CODES.iter().any(|(_, alpha_3)| *alpha_3 == country),
+        _ => false,
+    };
+    if known {
+        Ok(())
+    } else {
+        input(format!(
+            "country {:?} is not an ISO 3166 code such as \"US\" or \"USA\"",
+            country
+        ))
+    }
+}
+
+fn check_city_id(id: &str) -> Result<()> {
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) || id.parse::<u32>().is_err() {
+        return input(format!("city ID {:?} is not a number", id));
+    }
+    Ok(())
+}
+
+fn check_coordinates(lat: f32, lon: f32) -> Result<()> {
+    if !(-90.0..=90.0).contains(&lat) {
+        return input(format!("latitude {} is not within -90 to 90", lat));
+    }
+    if !(-180.0..=180.0).contains(&lon) {
+        return input(format!("longitude {} is not within -180 to 180", lon));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::COUNTRY_CODES;
+    use crate::{Error, LocationSpecifier};
+
+    fn message(result: crate::Result<LocationSpecifier>) -> String {
+        match result {
+            Err(Error::Input { msg }) => msg,
+            other => panic!("expected an input error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn country_codes_are_sorted() {
+        assert!(COUNTRY_CODES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn constructors_check_their_input() {
+        let london = LocationSpecifier::city("London", "gb").unwrap();
+        assert_eq!(
+            london.format(),
+            vec![("q".to_string(), "London,GB".to_string())]
+        );
+        assert!(LocationSpecifier::zip_code("55401", "").is_ok());
+        assert!(LocationSpecifier::circle(44.98, -93.26, 50).is_ok());
+
+        assert_eq!(
+            message(LocationSpecifier::coordinates(91.0, 0.0)),
+            "latitude 91 is not within -90 to 90"
+        );
+        assert_eq!(
+            message(LocationSpecifier::city("London", "UK")),
+            "country \"UK\" is not an ISO 3166 code such as \"US\" or \"USA\""
+        );
+        let minneapolis = LocationSpecifier::city("Minneapolis", "usa").unwrap();
+        assert_eq!(minneapolis.to_string(), "Minneapolis,USA");
+        assert!(LocationSpecifier::zip_code("10115", "DEU").is_ok());
+        assert!(message(LocationSpecifier::city("London", "GBX")).contains("GBX"));
+        assert_eq!(
+            message(LocationSpecifier::city_ids(&["5037649", "Minneapolis"])),
+            "city ID \"Minneapolis\" is not a number"
+        );
+        assert!(message(LocationSpecifier::circle(0.0, 0.0, 51)).contains("1 to 50"));
+        assert!(LocationSpecifier::bounding_box(12.0, 32.0, 15.0, 37.0, 10.0).is_ok());
+        assert!(message(LocationSpecifier::bounding_box(
+            15.0, 32.0, 12.0, 37.0, 10.0
+        ))
+        .contains("bounding box"));
+        // Across the antimeridian
+        assert!(message(LocationSpecifier::bounding_box(
+            170.0, -20.0, -170.0, -10.0, 10.0
+        ))
+        .contains("split in two"));
+    }
+
+    #[test]
+    fn every_variant_round_trips_through_text() {
+        for text in &[
+            "Minneapolis,US",
+            "Minneapolis",
+            "id:5037649",
+            "44.98,-93.26",
+            "zip:55401,US",
+            "zip:55401",
+            "box:12,32,15,37,10",
+            "circle:44.98,-93.26,10",
+            "ids:524901,703448",
+        ] {
+            let location: LocationSpecifier = text.parse().unwrap();
+            assert_eq!(&location.to_string(), text);
+        }
+        let location: LocationSpecifier = " minneapolis , us ".parse().unwrap();
+        assert_eq!(location.to_string(), "minneapolis,US");
+        assert!(matches!(
+            "St. John's".parse(),
+            Ok(LocationSpecifier::CityAndCountryName { .. })
+        ));
+    }
+
+    #[test]
+    fn parses_geo_uris() {
+        for uri in &[
+            "geo:44.98,-93.26",
+            "GEO:44.98,-93.26,250;u=35",
+            "geo:44.98,-93.26;crs=WGS84;u=35",
+        ] {
+            let location: LocationSpecifier = uri.parse().unwrap();
+            assert_eq!(location.to_string(), "44.98,-93.26");
+        }
+        assert!(message("geo:44.98,-93.26;crs=nad27".parse()).contains("nad27"));
+        assert!(message("geo:44.98".parse()).contains("not a geo URI"));
+        assert_eq!(
+            message("circle:44.98,north,10".parse()),
+            "\"north\" in \"circle:44.98,north,10\" is not a valid number"
+        );
+        assert!(message("box:1,2,3".parse()).contains("does not have the form"));
+        assert!(message("id:abc".parse()).contains("is not a number"));
+    }
+
+    #[test]
+    fn locations_compare_hash_and_serialize() {
+        use std::collections::HashSet;
+
+        let a = LocationSpecifier::Coordinates {
+            lat: 0.0,
+            lon: -0.0,
+        };
+        let b = LocationSpecifier::Coordinates {
+            lat: -0.0,
+            lon: 0.0,
+        };
+        let nan = LocationSpecifier::Coordinates {
+            lat: f32::NAN,
+            lon: 0.0,
+        };
+        assert_eq!(a, b);
+        assert_eq!(nan, nan.clone());
+        assert_ne!(a, nan);
+        assert_ne!(
+            LocationSpecifier::CityAndCountryName {
+                city: "55401".into(),
+                country: "US".into()
+            },
+            LocationSpecifier::ZipCode {
+                zip: "55401".into(),
+                country: "US".into()
+            }
+        );
+        let set: HashSet<_> = vec![a, b, nan.clone(), nan].into_iter().collect();
+        assert_eq!(set.len(), 2);
+
+        let circle: LocationSpecifier = "circle:44.98,-93.26,10".parse().unwrap();
+        let json = serde_json::to_string(&circle).unwrap();
+        assert_eq!(json, r#""circle:44.98,-93.26,10""#);
+        assert_eq!(
+            serde_json::from_str::<LocationSpecifier>(&json).unwrap(),
+            circle
+        );
+        let err = serde_json::from_str::<LocationSpecifier>(r#""id:abc""#).unwrap_err();
+        assert!(err.to_string().contains("is not a number"), "{}", err);
+    }
+
+    #[test]
+    fn city_ids_are_comma_separated() {
+        let ids = LocationSpecifier::city_ids(&["524901", "703448", "2643743"]).unwrap();
+        assert_eq!(
+            ids.format(),
+            vec![("id".to_string(), "524901,703448,2643743".to_string())]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::Mutex;
+
+use url::Url;
+
+use crate::transport::{HttpResponse, Transport};
+use crate::Result;
+
+#[cfg(feature = "async")]
+use crate::transport::{AsyncTransport, BoxFuture};
+
+struct Route {
+    path: String,
+    query: Vec<(String, String)>,
+    response: HttpResponse,
+}
+
+impl Route {
+    fn new(pattern: &str, response: HttpResponse) -> Route {
+        let (path, query) = match pattern.find('?') {
+            Some(i) => (&pattern[..i], &pattern[i + 1..]),
+            None => (pattern, ""),
+        };
+        Route {
+            path: path.trim_matches('/').to_string(),
+            query: url::form_urlencoded::parse(query.as_bytes())
+                .into_owned()
+                .collect(),
+            response,
+        }
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let path = url.path().trim_end_matches('/');
+        let path_matches =
+            self.path.is_empty() || path == self.path || path.ends_with(&format!("/{}", self.path));
+        path_matches
+            && self
+                .query
+                .iter()
+                .all(|pair| url.query_pairs().into_owned().any(|p| p == *pair))
+    }
+}
+
+/// An in-memory transport serving canned responses, for exercising a client
+/// without network access or an API key.
+///
+/// Responses are registered against a pattern made of the trailing segments
+/// of the endpoint path, optionally followed by query parameters the request
+/// must contain, e.g. `"weather"` or `"forecast/daily?cnt=3"`. When several
+/// patterns match the longest one wins, ties going to the one added last.
+/// Unmatched requests get a 404 with an OpenWeatherMap style error body.
+///
+/// ```
+/// # #[cfg(feature = "blocking")] {
+/// use openweather::{Client, LocationSpecifier, MockTransport};
+///
+/// let mock = MockTransport::new().with_json("uvi", r#"{
+///     "lat": 44.98, "lon": -93.26, "data_iso": "2020-06-01T12:00:00Z",
+///     "date": 1591012800, "value": 7.1
+/// }"#);
+/// let client = Client::builder("KEY").build_with_transport(mock);
+/// let loc = LocationSpecifier::CityId("5037649".to_string());
+/// assert_eq!(client.get_current_uv_index(&loc)?.value, 7.1);
+/// assert_eq!(client.transport().requests().len(), 1);
+/// # }
+/// # Ok::<(), openweather::Error>(())
+/// ```
+#[derive(Default)]
+pub struct MockTransport {
+    routes: Vec<Route>,
+    requests: Mutex<Vec<Url>>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        MockTransport::default()
+    }
+
+    /// Answers requests matching `pattern` with `body` and a 200 status.
+    pub fn with_json(self, pattern: &str, body: &str) -> Self {
+        self.with_response(
+            pattern,
+            HttpResponse {
+                status: 200,
+                headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+                body: body.as_bytes().to_vec(),
+            },
+        )
+    }
+
+    /// Answers requests matching `pattern` with `response`.
+    pub fn with_response(mut self, pattern: &str, response: HttpResponse) -> Self {
+        self.routes.push(Route::new(pattern, response));
+        self
+    }
+
+    /// Every URL requested so far, in order.
+    pub fn requests(&self) -> Vec<Url> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn respond(&self, url: &Url) -> HttpResponse {
+        self.requests.lock().unwrap().push(url.clone());
+
+        let route = self
+            .routes
+            .iter()
+            .filter(|route| route.matches(url))
+            .max_by_key(|route| (route.path.len(), route.query.len()));
+        match route {
+            Some(route) => route.response.clone(),
+            None => HttpResponse {
+                status: 404,
+                headers: vec![],
+                body: format!(
+                    "{{\"cod\":404,\"message\":\"no mock response for {}\"}}",
+                    url.path()
+                )
+                .into_bytes(),
+            },
+        }
+    }
+}
+
+impl std::fmt::Debug for MockTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockTransport")
+            .field("routes", &self.routes.len())
+            .field("requests", &self.requests.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Transport for MockTransport {
+    fn get(&self, url: &Url) -> Result<HttpResponse> {
+        Ok(self.respond(url))
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncTransport for MockTransport {
+    fn get<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, Result<HttpResponse>> {
+        let response = self.respond(url);
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockTransport;
+    use url::Url;
+
+    fn status(mock: &MockTransport, url: &str) -> u16 {
+        mock.respond(&Url::parse(url).unwrap()).status
+    }
+
+    #[test]
+    fn longest_matching_pattern_wins() {
+        let mock = MockTransport::new()
+            .with_json("forecast", "{}")
+            .with_response(
+                "uvi/forecast",
+                crate::HttpResponse {
+                    status: 201,
+                    ..Default::default()
+                },
+            );
+        assert_eq!(status(&mock, "http://h/data/2.5/forecast?id=1"), 200);
+        assert_eq!(status(&mock, "http://h/data/2.5/uvi/forecast?id=1"), 201);
+        assert_eq!(status(&mock, "http://h/data/2.5/forecast/daily?id=1"), 404);
+    }
+
+    #[test]
+    fn query_parameters_must_be_present() {
+        let mock = MockTransport::new().with_json("weather?id=1", "{}");
+        assert_eq!(status(&mock, "http://h/data/2.5/weather?id=1&APPID=k"), 200);
+        assert_eq!(status(&mock, "http://h/data/2.5/weather?id=2&APPID=k"), 404);
+        assert_eq!(mock.requests().len(), 2);
+    }
+}
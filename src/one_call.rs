@@ -0,0 +1,136 @@
+use crate::Coordinates;
+
+/// A block of data returned by the One Call API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OneCallBlock {
+    /// Current weather
+    Current,
+    /// Precipitation for the next 60 minutes, minute by minute
+    Minutely,
+    /// Forecast for the next 48 hours
+    Hourly,
+    /// Forecast for the next 8 days
+    Daily,
+    /// Government weather alerts
+    Alerts,
+}
+
+impl OneCallBlock {
+    pub const ALL: [OneCallBlock; 5] = [
+        OneCallBlock::Current,
+        OneCallBlock::Minutely,
+        OneCallBlock::Hourly,
+        OneCallBlock::Daily,
+        OneCallBlock::Alerts,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            OneCallBlock::Current => "current",
+            OneCallBlock::Minutely => "minutely",
+            OneCallBlock::Hourly => "hourly",
+            OneCallBlock::Daily => "daily",
+            OneCallBlock::Alerts => "alerts",
+        }
+    }
+}
+
+/// A One Call API 3.0 request, selecting the blocks the response includes.
+///
+/// Every block is included unless excluded, blocks left out of the request
+/// are `None` in the `WeatherReportOneCall3`.
+///
+/// ```
+/// use openweather::{Coordinates, OneCall, OneCallBlock};
+///
+/// let coordinates = Coordinates { lat: 44.98, lon: -93.26 };
+/// let nowcast = OneCall::new(&coordinates).only(&[OneCallBlock::Minutely, OneCallBlock::Hourly]);
+/// let no_alerts = OneCall::new(&coordinates).exclude(OneCallBlock::Alerts);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct OneCall {
+    lat: f32,
+    lon: f32,
+    blocks: Vec<OneCallBlock>,
+}
+
+impl OneCall {
+    /// A request for every block at `coordinates`.
+    pub fn new(coordinates: &Coordinates) -> OneCall {
+        OneCall {
+            lat: coordinates.lat,
+            lon: coordinates.lon,
+            blocks: OneCallBlock::ALL.to_vec(),
+        }
+    }
+
+    /// Includes exactly the given blocks.
+    pub fn only(mut self, blocks: &[OneCallBlock]) -> Self {
+        self.blocks = blocks.to_vec();
+        self
+    }
+
+    pub fn include(mut self, block: OneCallBlock) -> Self {
+        if !self.blocks.contains(&block) {
+            self.blocks.push(block);
+        }
+        self
+    }
+
+    pub fn exclude(mut self, block: OneCallBlock) -> Self {
+        self.blocks.retain(|b| *b != block);
+        self
+    }
+
+    pub fn includes(&self, block: OneCallBlock) -> bool {
+        self.blocks.contains(&block)
+    }
+
+    pub(crate) fn format(&self) -> Vec<(String, String)> {
+        let mut params = vec![
+            ("lat".to_string(), format!("{}", self.lat)),
+            ("lon".to_string(), format!("{}", self.lon)),
+        ];
+        let excluded: Vec<&str> = OneCallBlock::ALL
+            .iter()
+            .filter(|block| !self.includes(**block))
+            .map(|block| block.name())
+            .collect();
+        if !excluded.is_empty() {
+            params.push(("exclude".to_string(), excluded.join(",")));
+        }
+        params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OneCall, OneCallBlock};
+    use crate::Coordinates;
+
+    fn exclude(request: &OneCall) -> Option<String> {
+        request
+            .format()
+            .into_iter()
+            .find(|(key, _)| key == "exclude")
+            .map(|(_, value)| value)
+    }
+
+    #[test]
+    fn excludes_blocks_not_included() {
+        let coordinates = Coordinates { lat: 1.0, lon: 2.0 };
+        let all = OneCall::new(&coordinates);
+        assert_eq!(exclude(&all), None);
+
+        let hourly = all.clone().only(&[OneCallBlock::Hourly]);
+        assert_eq!(
+            exclude(&hourly),
+            Some("current,minutely,daily,alerts".to_string())
+        );
+
+        let no_alerts = all.exclude(OneCallBlock::Alerts);
+        assert_eq!(exclude(&no_alerts), Some("alerts".to_string()));
+        let alerts = no_alerts.include(OneCallBlock::Alerts);
+        assert_eq!(exclude(&alerts), None);
+    }
+}
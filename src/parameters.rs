@@ -1,31 +1,48 @@
-#[derive(Default, Debug)]
+use std::fmt;
+use std::str::FromStr;
+
+use serde::de::Deserializer;
+use serde::ser::Serializer;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// Serialized as a map of the settings that are set, e.g.
+/// `{"unit": "metric", "lang": "de"}`.
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq, Hash)]
+#[serde(default)]
 pub struct Settings {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub unit: Option<Unit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub lang: Option<Language>,
 }
 
 impl Settings {
     pub fn format(&self) -> Vec<(String, String)> {
         let mut res: Vec<(String, String)> = Vec::new();
-        add_param(&mut res, self.unit);
-        add_param(&mut res, self.lang);
+        add_param(&mut res, self.unit.as_ref());
+        add_param(&mut res, self.lang.as_ref());
         res
     }
 }
 
-fn add_param<T: FormatParameters>(settings: &mut Vec<(String, String)>, param: Option<T>) {
-    param.map(|v: T| v.format().map(|u: (String, String)| settings.push(u)));
+fn add_param<T: FormatParameters>(settings: &mut Vec<(String, String)>, param: Option<&T>) {
+    param.map(|v: &T| v.format().map(|u: (String, String)| settings.push(u)));
 }
 
 trait FormatParameters {
     fn format(&self) -> Option<(String, String)>;
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Serialized as its `units` parameter value, e.g. `"metric"`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "lowercase")]
 pub enum Unit {
-    // Celcius, default
+    // Celcius
     Metric,
-    // Kelvin
+    // Kelvin, the API's default
+    #[default]
     Standard,
     // Fahrenheit
     Imperial,
@@ -42,7 +59,11 @@ impl FormatParameters for Unit {
 }
 
 /// Translation is only applied for the description field!
-#[derive(Debug, Clone, Copy)]
+///
+/// `Display` writes the `lang` parameter value, e.g. `de`, and `FromStr`
+/// reads it as well as BCP-47 tags such as `pt-BR` or `zh-Hant`. Serialized
+/// as its `lang` parameter value, any other code deserializing as `Custom`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Language {
     Arabic,
     Bulgarian,
@@ -77,49 +98,251 @@ pub enum Language {
     Vietnamese,
     ChineseSimplified,
     ChineseTraditional,
+    Afrikaans,
+    Albanian,
+    Azerbaijani,
+    Basque,
+    Danish,
+    Hebrew,
+    Hindi,
+    Indonesian,
+    Norwegian,
+    PortugueseBrasil,
+    Serbian,
+    Thai,
+    Zulu,
+    /// A language code the crate does not know, sent as is
+    Custom(String),
+}
+
+/// Every language with its `lang` code and its ISO 639-1 code. The API
+/// documents `al`, `cz`, `kr` and `la` rather than the ISO codes, which are
+/// read but not sent.
+const LANGUAGES: [(Language, &str, &str); 46] = [
+    (Language::Arabic, "ar", "ar"),
+    (Language::Bulgarian, "bg", "bg"),
+    (Language::Catalan, "ca", "ca"),
+    (Language::Czech, "cz", "cs"),
+    (Language::German, "de", "de"),
+    (Language::Greek, "el", "el"),
+    (Language::English, "en", "en"),
+    (Language::PersianFarsi, "fa", "fa"),
+    (Language::Finnish, "fi", "fi"),
+    (Language::French, "fr", "fr"),
+    (Language::Galician, "gl", "gl"),
+    (Language::Croatian, "hr", "hr"),
+    (Language::Hungarian, "hu", "hu"),
+    (Language::Italian, "it", "it"),
+    (Language::Japanese, "ja", "ja"),
+    (Language::Korean, "kr", "ko"),
+    (Language::Latvian, "la", "lv"),
+    (Language::Lithuanian, "lt", "lt"),
+    (Language::Macedonian, "mk", "mk"),
+    (Language::Dutch, "nl", "nl"),
+    (Language::Polish, "pl", "pl"),
+    (Language::Portuguese, "pt", "pt"),
+    (Language::Romanian, "ro", "ro"),
+    (Language::Russian, "ru", "ru"),
+    (Language::Swedish, "sv", "sv"),
+    (Language::Slovak, "sk", "sk"),
+    (Language::Slovenian, "sl", "sl"),
+    (Language::Spanish, "es", "es"),
+    (Language::Turkish, "tr", "tr"),
+    (Language::Ukrainian, "uk", "uk"),
+    (Language::Vietnamese, "vi", "vi"),
+    (Language::ChineseSimplified, "zh_cn", "zh"),
+    (Language::ChineseTraditional, "zh_tw", "zh"),
+    (Language::Afrikaans, "af", "af"),
+    (Language::Albanian, "al", "sq"),
+    (Language::Azerbaijani, "az", "az"),
+    (Language::Basque, "eu", "eu"),
+    (Language::Danish, "da", "da"),
+    (Language::Hebrew, "he", "he"),
+    (Language::Hindi, "hi", "hi"),
+    (Language::Indonesian, "id", "id"),
+    (Language::Norwegian, "no", "no"),
+    (Language::PortugueseBrasil, "pt_br", "pt"),
+    (Language::Serbian, "sr", "sr"),
+    (Language::Thai, "th", "th"),
+    (Language::Zulu, "zu", "zu"),
+];
+
+/// Codes the API used to document, read for compatibility.
+const LEGACY_CODES: [(Language, &str); 3] = [
+    (Language::Swedish, "se"),
+    (Language::Spanish, "sp"),
+    (Language::Ukrainian, "ua"),
+];
+
+impl Language {
+    /// The value of the `lang` parameter.
+    pub fn code(&self) -> &str {
+        match self {
+            Language::Custom(code) => code,
+            language => LANGUAGES
+                .iter()
+                .find(|(known, _, _)| known == language)
+                .map(|(_, code, _)| *code)
+                .expect("every language has a code"),
+        }
+    }
+
+    /// The most preferred supported language of an `Accept-Language` header,
+    /// e.g. `"pt-BR,pt;q=0.9,en;q=0.8"`, `None` when there is none.
+    pub fn from_accept_language(header: &str) -> Option<Language> {
+        let mut ranges: Vec<(f32, &str)> = header
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse().ok())
+                    .unwrap_or(1.0);
+                Some((quality, tag))
+            })
+            .filter(|&(quality, tag)| quality > 0.0 && tag != "*")
+            .collect();
+        // Stable, so equally preferred languages keep the header's order
+        ranges.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        ranges.into_iter().find_map(|(_, tag)| tag.parse().ok())
+    }
+
+    /// The language of a BCP-47 tag, from its language subtag and, for
+    /// Chinese and Portuguese, its script or region.
+    fn from_bcp47(tag: &str) -> Option<Language> {
+        let mut subtags = tag.split('-');
+        let primary = subtags.next()?;
+        let rest: Vec<&str> = subtags.collect();
+        let script = rest.iter().find(|subtag| subtag.len() == 4).copied();
+        let region = rest.iter().find(|subtag| subtag.len() == 2).copied();
+        match primary {
+            "zh" => match (script, region) {
+                (Some("hant"), _)
+                | (None, Some("tw"))
+                | (None, Some("hk"))
+                | (None, Some("mo")) => Some(Language::ChineseTraditional),
+                _ => Some(Language::ChineseSimplified),
+            },
+            "pt" if region == Some("br") => Some(Language::PortugueseBrasil),
+            "nb" | "nn" => Some(Language::Norwegian),
+            // Deprecated ISO 639 codes
+            "iw" => Some(Language::Hebrew),
+            "in" => Some(Language::Indonesian),
+            primary => LANGUAGES
+                .iter()
+                .find(|(_, _, iso)| *iso == primary)
+                .map(|(language, _, _)| language.clone()),
+        }
+    }
 }
 
 impl FormatParameters for Language {
     fn format(&self) -> Option<(String, String)> {
-        use Language::*;
-        Some((
-            String::from("lang"),
-            match self {
-                Arabic => "ar",
-                Bulgarian => "bg",
-                Catalan => "ca",
-                Czech => "cz",
-                German => "de",
-                Greek => "el",
-                English => "en",
-                PersianFarsi => "fa",
-                Finnish => "fi",
-                French => "fr",
-                Galician => "gl",
-                Croatian => "hr",
-                Hungarian => "hu",
-                Italian => "it",
-                Japanese => "ja",
-                Korean => "kr",
-                Latvian => "la",
-                Lithuanian => "lt",
-                Macedonian => "mk",
-                Dutch => "nl",
-                Polish => "pl",
-                Portuguese => "pt",
-                Romanian => "ro",
-                Russian => "ru",
-                Swedish => "se",
-                Slovak => "sk",
-                Slovenian => "sl",
-                Spanish => "es",
-                Turkish => "tr",
-                Ukrainian => "ua",
-                Vietnamese => "vi",
-                ChineseSimplified => "zh_cn",
-                ChineseTraditional => "zh_tw",
-            }
-            .to_string(),
-        ))
+        Some((String::from("lang"), self.code().to_string()))
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Reads a `lang` code, `la` being Latvian as for the API rather than Latin,
+/// or a BCP-47 tag, ignoring case.
+impl FromStr for Language {
+    type Err = Error;
+
+    fn from_str(text: &str) -> crate::Result<Language> {
+        let tag = text.trim().to_ascii_lowercase().replace('_', "-");
+        let code = tag.replace('-', "_");
+        LANGUAGES
+            .iter()
+            .map(|(language, code, _)| (language, *code))
+            .chain(
+                LEGACY_CODES
+                    .iter()
+                    .map(|(language, code)| (language, *code)),
+            )
+            .find(|(_, known)| *known == code)
+            .map(|(language, _)| language.clone())
+            .or_else(|| Language::from_bcp47(&tag))
+            .ok_or_else(|| Error::Input {
+                msg: format!("{:?} is not a language the API supports", text),
+            })
+    }
+}
+
+impl serde::Serialize for Language {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Language {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        Ok(code.parse().unwrap_or(Language::Custom(code)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Language, Settings, Unit};
+
+    #[test]
+    fn settings_serialize_as_their_parameter_values() {
+        let settings = Settings {
+            unit: Some(Unit::Metric),
+            lang: Some(Language::ChineseSimplified),
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(json, r#"{"unit":"metric","lang":"zh_cn"}"#);
+        assert_eq!(serde_json::from_str::<Settings>(&json).unwrap(), settings);
+
+        assert_eq!(serde_json::to_string(&Settings::default()).unwrap(), "{}");
+        let partial: Settings = serde_json::from_str(r#"{"lang":"de"}"#).unwrap();
+        assert_eq!(partial.lang, Some(Language::German));
+        assert_eq!(
+            serde_json::from_str::<Language>(r#""xx""#).unwrap(),
+            Language::Custom("xx".to_string())
+        );
+    }
+
+    #[test]
+    fn languages_parse_from_codes_and_tags() {
+        let parse = |text: &str| text.parse::<Language>().unwrap();
+        for (language, code, _) in super::LANGUAGES.iter() {
+            assert_eq!(&parse(code), language);
+            assert_eq!(&language.to_string(), code);
+        }
+        assert_eq!(parse("pt-BR"), Language::PortugueseBrasil);
+        assert_eq!(parse("pt-PT"), Language::Portuguese);
+        assert_eq!(parse("zh-Hant"), Language::ChineseTraditional);
+        assert_eq!(parse("zh-Hans-TW"), Language::ChineseSimplified);
+        assert_eq!(parse("zh-HK"), Language::ChineseTraditional);
+        assert_eq!(parse("ZH_CN"), Language::ChineseSimplified);
+        assert_eq!(parse("cs-CZ"), Language::Czech);
+        assert_eq!(parse("ko"), Language::Korean);
+        assert_eq!(parse("lv"), Language::Latvian);
+        assert_eq!(parse("la"), Language::Latvian);
+        assert_eq!(parse("nb-NO"), Language::Norwegian);
+        assert_eq!(parse("sr-Latn-RS"), Language::Serbian);
+        assert_eq!(parse("ua"), Language::Ukrainian);
+        assert!("tlh".parse::<Language>().is_err());
+        assert_eq!(Language::Custom("xx".to_string()).to_string(), "xx");
+    }
+
+    #[test]
+    fn picks_the_preferred_language_of_an_accept_language_header() {
+        let pick = Language::from_accept_language;
+        assert_eq!(
+            pick("pt-BR,pt;q=0.9,en;q=0.8"),
+            Some(Language::PortugueseBrasil)
+        );
+        assert_eq!(pick("tlh, en;q=0.5, de;q=0.7"), Some(Language::German));
+        assert_eq!(pick("de;q=0, *"), None);
+        assert_eq!(pick(""), None);
     }
 }
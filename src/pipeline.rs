@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use log::debug;
+use serde::de::DeserializeOwned;
+
+use crate::client::Config;
+use crate::disk_cache::{Freshness, Revalidation, StoredResponse};
+use crate::request::{self, Request};
+use crate::weather_types::Report;
+use crate::{Error, HttpResponse, Result, Url};
+
+/// The report of a request in the in-memory cache, `key` being its cache
+/// key.
+pub(crate) fn cached<R>(config: &Config, key: &str) -> Option<Result<R>>
+where
+    R: DeserializeOwned + Report,
+{
+    let res = config.cache()?.get(key)?;
+    debug!("Cache hit");
+    Some(request::parse(&res, config.unit()))
+}
+
+/// What to do with the disk cache entry of a request.
+pub(crate) enum Stored {
+    /// Return it, it is fresh
+    Use(HttpResponse),
+    /// Return it while a fresh copy is fetched in the background, unless
+    /// one already is and there is no `Revalidation` to run
+    Revalidate(HttpResponse, Option<Revalidation>),
+    /// Send the request, falling back on the entry when that fails
+    FallBack(Option<HttpResponse>),
+}
+
+impl Stored {
+    pub fn new(config: &Config, key: &str, stored: Option<StoredResponse>) -> Stored {
+        match stored {
+            Some(stored) if stored.freshness == Freshness::Fresh => {
+                debug!("Disk cache hit");
+                Stored::Use(stored.response)
+            }
+            Some(stored) if stored.freshness == Freshness::Revalidate => {
+                debug!("Disk cache hit, revalidating");
+                Stored::Revalidate(stored.response, config.revalidations().start(key))
+            }
+            stored => Stored::FallBack(stored.map(|stored| stored.response)),
+        }
+    }
+}
+
+/// The outcome of sending a request, or of the stale disk cache entry when
+/// sending failed with a retryable error.
+pub(crate) fn fall_back<R>(
+    config: &Config,
+    result: Result<R>,
+    stored: Option<HttpResponse>,
+) -> Result<R>
+where
+    R: DeserializeOwned + Report,
+{
+    match (result, stored) {
+        (Err(err), Some(stored)) if err.is_retryable() => {
+            debug!("{}, using the stale disk cache entry", err);
+            request::parse(&stored, config.unit())
+        }
+        (result, _) => result,
+    }
+}
+
+/// Keeps a fresh response holding the report of a request in the in-memory
+/// cache, the clients writing the disk cache themselves.
+pub(crate) fn store<R>(config: &Config, request: &Request<R>, key: &str, res: HttpResponse) {
+    if let Some(cache) = config.cache() {
+        cache.insert(key.to_string(), request.endpoint(), res);
+    }
+}
+
+/// What to do after an attempt at sending a request.
+pub(crate) enum Next<R> {
+    /// Done, with the response to cache
+    Report(R, HttpResponse),
+    Failed(Error),
+    /// Send the request again once the delay has passed
+    Retry(Duration),
+}
+
+/// The attempts at sending a request: retrying it as the `RetryPolicy`
+/// allows and with another key when the key pool leaves out the one used.
+pub(crate) struct Attempts<'a, R> {
+    config: &'a Config,
+    request: &'a Request<R>,
+    /// The key of the attempt in flight
+    index: Option<usize>,
+    attempt: u32,
+    switches: usize,
+    /// The error of the key that was left out for the attempt in flight
+    switched_from: Option<Error>,
+    /// Whether no key was left to switch to
+    exhausted: bool,
+}
+
+impl<'a, R> Attempts<'a, R>
+where
+    R: DeserializeOwned + Report,
+{
+    pub fn new(config: &'a Config, request: &'a Request<R>) -> Attempts<'a, R> {
+        Attempts {
+            config,
+            request,
+            index: None,
+            attempt: 1,
+            switches: 0,
+            switched_from: None,
+            exhausted: false,
+        }
+    }
+
+    /// Picks the key of the next attempt, returning the URL to send. Only
+    /// the request actually sent picks one, not those sharing its response.
+    pub fn url(&mut self) -> Result<Url> {
+        let (index, api_key) = match self.config.keys().pick(self.request.api) {
+            Ok(picked) => picked,
+            Err(err) => {
+                self.exhausted = true;
+                // Having switched keys, the error of the one left out
+                return Err(self.switched_from.take().unwrap_or(err));
+            }
+        };
+        self.index = Some(index);
+        self.switched_from = None;
+        let url = self.config.url(self.request, &api_key)?;
+        debug!("Url: {:?}", url.as_str());
+        Ok(url)
+    }
+
+    pub fn next(&mut self, result: Result<HttpResponse>) -> Next<R> {
+        let unit = self.config.unit();
+        let err = match result.and_then(|res| Ok((request::parse(&res, unit)?, res))) {
+            Ok((report, res)) => return Next::Report(report, res),
+            Err(err) => err,
+        };
+        if self.exhausted {
+            return Next::Failed(err);
+        }
+        let keys = self.config.keys();
+        // A key left out for no time at all may be picked again.
+        if let Some(index) = self.index.take() {
+            if keys.report(index, &err) && self.switches < keys.len(self.request.api) {
+                debug!("{}, trying another key", err);
+                self.switches += 1;
+                self.switched_from = Some(err);
+                return Next::Retry(Duration::ZERO);
+            }
+        }
+        match self.config.retry().delay(self.attempt, &err) {
+            Some(delay) => {
+                debug!(
+                    "Attempt {} failed: {}, retrying in {:?}",
+                    self.attempt, err, delay
+                );
+                self.attempt += 1;
+                Next::Retry(delay)
+            }
+            None => Next::Failed(err),
+        }
+    }
+}
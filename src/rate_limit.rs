@@ -0,0 +1,243 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{Error, Result};
+
+const MINUTE: Duration = Duration::from_secs(60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// What a client does with a request exceeding its `RateLimit`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitMode {
+    /// Wait until a call is available, the default
+    Wait,
+    /// Fail right away with `Error::RateLimited`
+    Fail,
+}
+
+/// A client side limit on the calls a client makes, to stay within the
+/// quotas of an OpenWeatherMap plan, e.g. 60 calls per minute on the free
+/// plan.
+///
+/// Each limit is a token bucket: it starts full, every call takes a token and
+/// tokens come back at an even rate over the period, so short bursts up to
+/// the limit are allowed. Clones of a client, and clients made with
+/// `with_settings`, share their limiter.
+///
+/// ```
+/// use openweather::{RateLimit, RateLimitMode};
+///
+/// let limit = RateLimit::new()
+///     .per_minute(60)
+///     .per_day(1000)
+///     .mode(RateLimitMode::Fail);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RateLimit {
+    per_minute: Option<u32>,
+    per_day: Option<u32>,
+    mode: Option<RateLimitMode>,
+}
+
+impl RateLimit {
+    /// No limit until one is set.
+    pub fn new() -> RateLimit {
+        RateLimit::default()
+    }
+
+    pub fn per_minute(mut self, calls: u32) -> Self {
+        self.per_minute = Some(calls.max(1));
+        self
+    }
+
+    pub fn per_day(mut self, calls: u32) -> Self {
+        self.per_day = Some(calls.max(1));
+        self
+    }
+
+    pub fn mode(mut self, mode: RateLimitMode) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+}
+
+/// Counters of a client's `RateLimit`, see `Client::rate_limit_stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RateLimitStats {
+    /// Calls let through, including the delayed ones
+    pub allowed: u64,
+    /// Calls that had to wait for a free slot
+    pub delayed: u64,
+    /// Calls failed with `Error::RateLimited`
+    pub rejected: u64,
+    /// Calls available right now under the per minute limit
+    pub remaining_minute: Option<u32>,
+    /// Calls available right now under the per day limit
+    pub remaining_day: Option<u32>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    period: Duration,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(calls: u32, period: Duration, now: Instant) -> Bucket {
+        Bucket {
+            capacity: calls.into(),
+            period,
+            tokens: calls.into(),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.tokens = (self.tokens
+            + elapsed.as_secs_f64() / self.period.as_secs_f64() * self.capacity)
+            .min(self.capacity);
+        self.updated = now;
+    }
+
+    /// How long until a whole token is available.
+    fn wait(&self) -> Duration {
+        if self.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+        self.period.mul_f64((1.0 - self.tokens) / self.capacity)
+    }
+
+    fn remaining(&self) -> u32 {
+        self.tokens.max(0.0) as u32
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    minute: Option<Bucket>,
+    day: Option<Bucket>,
+    stats: RateLimitStats,
+}
+
+impl State {
+    fn buckets(&mut self) -> impl Iterator<Item = &mut Bucket> {
+        self.minute.iter_mut().chain(self.day.iter_mut())
+    }
+}
+
+/// The shared state of a client's `RateLimit`.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    mode: RateLimitMode,
+    state: Mutex<State>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: &RateLimit) -> RateLimiter {
+        let now = Instant::now();
+        RateLimiter {
+            mode: limit.mode.unwrap_or(RateLimitMode::Wait),
+            state: Mutex::new(State {
+                minute: limit
+                    .per_minute
+                    .map(|calls| Bucket::new(calls, MINUTE, now)),
+                day: limit.per_day.map(|calls| Bucket::new(calls, DAY, now)),
+                stats: RateLimitStats::default(),
+            }),
+        }
+    }
+
+    /// Takes a call from every bucket, returning how long to wait before
+    /// sending it. Waiting calls reserve their token right away, so callers
+    /// waiting concurrently are spaced out rather than all woken at once.
+    pub fn acquire(&self) -> Result<Duration> {
+        self.acquire_at(Instant::now())
+    }
+
+    fn acquire_at(&self, now: Instant) -> Result<Duration> {
+        let mut state = self.state.lock().unwrap();
+        let wait = state
+            .buckets()
+            .map(|bucket| {
+                bucket.refill(now);
+                bucket.wait()
+            })
+            .max()
+            .unwrap_or(Duration::ZERO);
+
+        if wait > Duration::ZERO && self.mode == RateLimitMode::Fail {
+            state.stats.rejected += 1;
+            return Err(Error::RateLimited { retry_after: wait });
+        }
+        for bucket in state.buckets() {
+            bucket.tokens -= 1.0;
+        }
+        state.stats.allowed += 1;
+        if wait > Duration::ZERO {
+            state.stats.delayed += 1;
+        }
+        Ok(wait)
+    }
+
+    pub fn stats(&self) -> RateLimitStats {
+        self.stats_at(Instant::now())
+    }
+
+    fn stats_at(&self, now: Instant) -> RateLimitStats {
+        let mut state = self.state.lock().unwrap();
+        for bucket in state.buckets() {
+            bucket.refill(now);
+        }
+        RateLimitStats {
+            remaining_minute: state.minute.as_ref().map(Bucket::remaining),
+            remaining_day: state.day.as_ref().map(Bucket::remaining),
+            ..state.stats
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::RateLimiter;
+    use crate::{Error, RateLimit, RateLimitMode};
+
+    #[test]
+    fn waits_for_the_bucket_to_refill() {
+        let limiter = RateLimiter::new(&RateLimit::new().per_minute(2));
+        let start = Instant::now();
+        assert_eq!(limiter.acquire_at(start).unwrap(), Duration::ZERO);
+        assert_eq!(limiter.acquire_at(start).unwrap(), Duration::ZERO);
+        assert_eq!(limiter.acquire_at(start).unwrap(), Duration::from_secs(30));
+
+        let stats = limiter.stats_at(start + Duration::from_secs(90));
+        assert_eq!((stats.allowed, stats.delayed), (3, 1));
+        assert_eq!(stats.remaining_minute, Some(2));
+        assert_eq!(stats.remaining_day, None);
+    }
+
+    #[test]
+    fn fails_once_the_smallest_limit_is_used_up() {
+        let limit = RateLimit::new()
+            .per_minute(10)
+            .per_day(1)
+            .mode(RateLimitMode::Fail);
+        let limiter = RateLimiter::new(&limit);
+        let start = Instant::now();
+        assert!(limiter.acquire_at(start).is_ok());
+        match limiter.acquire_at(start + Duration::from_secs(60)) {
+            Err(Error::RateLimited { retry_after }) => {
+                assert!(retry_after > Duration::from_secs(23 * 60 * 60))
+            }
+            other => panic!("expected a rate limit error, got {:?}", other),
+        }
+        let stats = limiter.stats_at(start + Duration::from_secs(60));
+        assert_eq!((stats.allowed, stats.rejected), (1, 1));
+        assert_eq!(stats.remaining_minute, Some(10));
+        assert_eq!(stats.remaining_day, Some(0));
+    }
+}
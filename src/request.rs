@@ -0,0 +1,459 @@
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use log::debug;
+
+use crate::weather_types::*;
+use time::{Date, UtcOffset};
+
+use crate::{
+    ApiError, Endpoint, Error, HttpResponse, LocationSpecifier, OneCall, Result, Timestamp, Unit,
+};
+
+static GEOCODING_LIMIT: u8 = 5;
+
+/// The OpenWeatherMap API family a request belongs to, each living under its
+/// own versioned path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Api {
+    /// `data/{version}`, the version being configurable on the client.
+    Data,
+    /// `data/3.0`, the One Call API 3.0 subscription.
+    OneCall,
+    /// `geo/1.0`, which ignores units and language.
+    Geo,
+}
+
+/// An API call that has not been sent yet: the endpoint path relative to the
+/// API base and its query parameters, without the key or settings.
+pub(crate) struct Request<T> {
+    pub api: Api,
+    pub path: &'static str,
+    pub params: Vec<(String, String)>,
+    response: PhantomData<fn() -> T>,
+}
+
+impl<T> Request<T> {
+    fn new(path: &'static str, params: Vec<(String, String)>) -> Self {
+        Request {
+            api: Api::Data,
+            path,
+            params,
+            response: PhantomData,
+        }
+    }
+
+    fn one_call(path: &'static str, params: Vec<(String, String)>) -> Self {
+        Request {
+            api: Api::OneCall,
+            ..Request::new(path, params)
+        }
+    }
+
+    fn geo(path: &'static str, params: Vec<(String, String)>) -> Self {
+        Request {
+            api: Api::Geo,
+            ..Request::new(path, params)
+        }
+    }
+
+    /// The kind of endpoint, deciding how long a `Cache` keeps the response.
+    pub fn endpoint(&self) -> Endpoint {
+        match (self.api, self.path) {
+            (Api::Geo, _) => Endpoint::Geocoding,
+            (_, "forecast")
+            | (_, "forecast/daily")
+            | (_, "uvi/forecast")
+            | (_, "air_pollution/forecast") => Endpoint::Forecast,
+            (_, path)
+                if path.starts_with("history/")
+                    || path.ends_with("/history")
+                    || path == "onecall/timemachine"
+                    || path == "onecall/day_summary" =>
+            {
+                Endpoint::Historical
+            }
+            _ => Endpoint::Current,
+        }
+    }
+}
+
+/// Parses a response into the expected report, requested in `unit`. Error
+/// statuses, and error reports sent along with a success status, become
+/// `Error::Api`.
+pub(crate) fn parse<T>(response: &HttpResponse, unit: Unit) -> Result<T>
+where
+    T: serde::de::DeserializeOwned + Report,
+{
+    debug!("Status: {:?}", response.status);
+    let body = String::from_utf8_lossy(&response.body).into_owned();
+    debug!("Body_String: {}", body);
+
+    let report = serde_json::from_str::<ErrorReport>(&body).ok();
+    let retry_after = response
+        .header("Retry-After")
+        .and_then(|seconds| seconds.trim().parse().ok())
+        .map(Duration::from_secs);
+    if !(200..300).contains(&response.status) {
+        return Err(Error::Api(ApiError {
+            status: response.status,
+            report,
+            body,
+            retry_after,
+        }));
+    }
+    match serde_json::from_str::<T>(&body) {
+        Ok(mut val) => {
+            val.set_unit(unit);
+            Ok(val)
+        }
+        Err(source) => match report {
+            Some(report) if report.cod >= 400 => Err(Error::Api(ApiError {
+                status: u16::try_from(report.cod).unwrap_or(u16::MAX),
+                report: Some(report),
+                body,
+                retry_after,
+            })),
+            _ => Err(Error::MalformedResponse {
+                status: response.status,
+                source,
+                body,
+            }),
+        },
+    }
+}
+
+/// The parameters of a `LocationSpecifier` naming a single city, the
+/// multi-city ones only being accepted by `cities_current_weather`.
+fn single_city(location: &LocationSpecifier) -> Result<Vec<(String, String)>> {
+    match location {
+        LocationSpecifier::BoundingBox { .. }
+        | LocationSpecifier::Circle { .. }
+        | LocationSpecifier::CityIds(_) => Err(Error::Input {
+            msg: format!(
+                "{:?} specifies multiple cities, which only the multi-city current weather supports",
+                location
+            ),
+        }),
+        _ => {
+            location.validate()?;
+            Ok(location.format())
+        }
+    }
+}
+
+pub(crate) fn current_weather(
+    location: &LocationSpecifier,
+) -> Result<Request<WeatherReportCurrent>> {
+    Ok(Request::new("weather", single_city(location)?))
+}
+
+/// Picks the multi-city endpoint matching `location`: `group` for city IDs,
+/// `find` for a circle and `box/city` for a bounding box.
+pub(crate) fn cities_current_weather(
+    location: &LocationSpecifier,
+) -> Result<Request<WeatherReportCities>> {
+    match location {
+        LocationSpecifier::CityIds(_) => {
+            location.validate()?;
+            Ok(Request::new("group", location.format()))
+        }
+        LocationSpecifier::Circle { .. } => {
+            location.validate()?;
+            Ok(Request::new("find", location.format()))
+        }
+        LocationSpecifier::BoundingBox { .. } => {
+            location.validate()?;
+            Ok(Request::new("box/city", location.format()))
+        }
+        _ => Err(Error::Input {
+            msg: format!("{:?} does not specify multiple cities", location),
+        }),
+    }
+}
+
+pub(crate) fn forecast_5_day(location: &LocationSpecifier) -> Result<Request<WeatherReport5Day>> {
+    Ok(Request::new("forecast", single_city(location)?))
+}
+
+pub(crate) fn forecast_16_day(
+    location: &LocationSpecifier,
+    len: u8,
+) -> Result<Request<WeatherReport16Day>> {
+    if len > 16 || len == 0 {
+        return Err(Error::Input {
+            msg: format!("Only support 1 to 16 day forecasts but {:?} requested", len),
+        });
+    }
+    let mut params = single_city(location)?;
+    params.push(("cnt".to_string(), format!("{}", len)));
+    Ok(Request::new("forecast/daily", params))
+}
+
+pub(crate) fn one_call_current(coordinates: &Coordinates) -> Request<WeatherReportOneCall> {
+    let mut params = coordinate_params(coordinates);
+    params.push(("exclude".to_string(), "minutely,hourly".to_string()));
+    Request::new("onecall", params)
+}
+
+pub(crate) fn one_call_historical(
+    coordinates: &Coordinates,
+    dt: Timestamp,
+) -> Request<WeatherReportOneCallHistorical> {
+    let mut params = coordinate_params(coordinates);
+    params.push(("dt".to_string(), format!("{}", dt.unix())));
+    Request::new("onecall/timemachine", params)
+}
+
+pub(crate) fn one_call(request: &OneCall) -> Request<WeatherReportOneCall3> {
+    Request::one_call("onecall", request.format())
+}
+
+fn format_date(date: Date) -> String {
+    format!(
+        "{:04}-{:02}-{:02}",
+        date.year(),
+        u8::from(date.month()),
+        date.day()
+    )
+}
+
+fn format_utc_offset(offset: UtcOffset) -> Result<String> {
+    let offset = offset.whole_seconds();
+    // UTC-12:00 to UTC+14:00 are the offsets in use
+    if !(-12 * 3600..=14 * 3600).contains(&offset) {
+        return Err(Error::Input {
+            msg: format!(
+                "UTC offset must be between -12:00 and +14:00 but {:?}s requested",
+                offset
+            ),
+        });
+    }
+    let sign = if offset < 0 { '-' } else { '+' };
+    let minutes = offset.abs() / 60;
+    Ok(format!("{}{:02}:{:02}", sign, minutes / 60, minutes % 60))
+}
+
+pub(crate) fn one_call_day_summary(
+    coordinates: &Coordinates,
+    date: Date,
+    utc_offset: Option<UtcOffset>,
+) -> Result<Request<WeatherReportDaySummary>> {
+    let mut params = coordinate_params(coordinates);
+    params.push(("date".to_string(), format_date(date)));
+    if let Some(offset) = utc_offset {
+        params.push(("tz".to_string(), format_utc_offset(offset)?));
+    }
+    Ok(Request::one_call("onecall/day_summary", params))
+}
+
+pub(crate) fn one_call_overview(
+    coordinates: &Coordinates,
+    date: Option<Date>,
+) -> Request<WeatherReportOverview> {
+    let mut params = coordinate_params(coordinates);
+    if let Some(date) = date {
+        params.push(("date".to_string(), format_date(date)));
+    }
+    Request::one_call("onecall/overview", params)
+}
+
+pub(crate) fn historical_data(
+    location: &LocationSpecifier,
+    start: Timestamp,
+    end: Timestamp,
+) -> Result<Request<WeatherReportHistorical>> {
+    let mut params = single_city(location)?;
+    params.push(("type".to_string(), "hour".to_string()));
+    params.push(("start".to_string(), format!("{}", start.unix())));
+    params.push(("end".to_string(), format!("{}", end.unix())));
+    Ok(Request::new("history/city", params))
+}
+
+pub(crate) fn accumulated_temperature(
+    location: &LocationSpecifier,
+    start: Timestamp,
+    end: Timestamp,
+    threshold: u32,
+) -> Result<Request<WeatherAccumulatedTemperature>> {
+    let mut params = single_city(location)?;
+    params.push(("type".to_string(), "hour".to_string()));
+    params.push(("start".to_string(), format!("{}", start.unix())));
+    params.push(("end".to_string(), format!("{}", end.unix())));
+    params.push(("threshold".to_string(), format!("{}", threshold)));
+    Ok(Request::new("history/accumulated_temperature", params))
+}
+
+pub(crate) fn accumulated_precipitation(
+    location: &LocationSpecifier,
+    start: Timestamp,
+    end: Timestamp,
+    threshold: u32,
+) -> Result<Request<WeatherAccumulatedPrecipitation>> {
+    let mut params = single_city(location)?;
+    params.push(("type".to_string(), "hour".to_string()));
+    params.push(("start".to_string(), format!("{}", start.unix())));
+    params.push(("end".to_string(), format!("{}", end.unix())));
+    params.push(("threshold".to_string(), format!("{}", threshold)));
+    Ok(Request::new("history/accumulated_precipitation", params))
+}
+
+pub(crate) fn current_uv_index(location: &LocationSpecifier) -> Result<Request<UvIndex>> {
+    Ok(Request::new("uvi", single_city(location)?))
+}
+
+pub(crate) fn forecast_uv_index(
+    location: &LocationSpecifier,
+    len: u8,
+) -> Result<Request<ForecastUvIndex>> {
+    if len > 8 || len == 0 {
+        return Err(Error::Input {
+            msg: format!("Only support 1 to 8 day forecasts but {:?} requested", len),
+        });
+    }
+    let mut params = single_city(location)?;
+    params.push(("cnt".to_string(), format!("{}", len)));
+    Ok(Request::new("uvi/forecast", params))
+}
+
+pub(crate) fn historical_uv_index(
+    location: &LocationSpecifier,
+    start: Timestamp,
+    end: Timestamp,
+) -> Result<Request<HistoricalUvIndex>> {
+    let mut params = single_city(location)?;
+    params.push(("start".to_string(), format!("{}", start.unix())));
+    params.push(("end".to_string(), format!("{}", end.unix())));
+    Ok(Request::new("uvi/history", params))
+}
+
+fn coordinate_params(coordinates: &Coordinates) -> Vec<(String, String)> {
+    vec![
+        ("lat".to_string(), format!("{}", coordinates.lat)),
+        ("lon".to_string(), format!("{}", coordinates.lon)),
+    ]
+}
+
+pub(crate) fn current_air_pollution(coordinates: &Coordinates) -> Request<AirPollution> {
+    Request::new("air_pollution", coordinate_params(coordinates))
+}
+
+pub(crate) fn forecast_air_pollution(coordinates: &Coordinates) -> Request<AirPollution> {
+    Request::new("air_pollution/forecast", coordinate_params(coordinates))
+}
+
+pub(crate) fn historical_air_pollution(
+    coordinates: &Coordinates,
+    start: Timestamp,
+    end: Timestamp,
+) -> Request<AirPollution> {
+    let mut params = coordinate_params(coordinates);
+    params.push(("start".to_string(), format!("{}", start.unix())));
+    params.push(("end".to_string(), format!("{}", end.unix())));
+    Request::new("air_pollution/history", params)
+}
+
+fn check_geocoding_limit(limit: u8) -> Result<()> {
+    if limit > GEOCODING_LIMIT || limit == 0 {
+        return Err(Error::Input {
+            msg: format!(
+                "Only support 1 to {} geocoding results but {:?} requested",
+                GEOCODING_LIMIT, limit
+            ),
+        });
+    }
+    Ok(())
+}
+
+pub(crate) fn geocoding_direct(query: &str, limit: u8) -> Result<Request<Vec<GeocodingLocation>>> {
+    check_geocoding_limit(limit)?;
+    let params = vec![
+        ("q".to_string(), query.to_string()),
+        ("limit".to_string(), format!("{}", limit)),
+    ];
+    Ok(Request::geo("direct", params))
+}
+
+pub(crate) fn geocoding_reverse(
+    coordinates: &Coordinates,
+    limit: u8,
+) -> Result<Request<Vec<GeocodingLocation>>> {
+    check_geocoding_limit(limit)?;
+    let mut params = coordinate_params(coordinates);
+    params.push(("limit".to_string(), format!("{}", limit)));
+    Ok(Request::geo("reverse", params))
+}
+
+pub(crate) fn geocoding_zip(zip: &str, country: &str) -> Request<GeocodingZip> {
+    let zip = if country.is_empty() {
+        zip.to_string()
+    } else {
+        format!("{},{}", zip, country)
+    };
+    Request::geo("zip", vec![("zip".to_string(), zip)])
+}
+
+/// How the coordinates of a `LocationSpecifier` are looked up.
+pub(crate) enum Resolve {
+    Known(Coordinates),
+    Direct(Request<Vec<GeocodingLocation>>),
+    Zip(Request<GeocodingZip>),
+    Weather(Request<WeatherReportCurrent>),
+}
+
+pub(crate) fn resolve_coordinates(location: &LocationSpecifier) -> Result<Resolve> {
+    location.validate()?;
+    match location {
+        LocationSpecifier::Coordinates { lat, lon } => Ok(Resolve::Known(Coordinates {
+            lat: *lat,
+            lon: *lon,
+        })),
+        LocationSpecifier::CityAndCountryName { city, country } => {
+            let query = if country.is_empty() {
+                city.to_string()
+            } else {
+                format!("{},{}", city, country)
+            };
+            Ok(Resolve::Direct(geocoding_direct(&query, 1)?))
+        }
+        LocationSpecifier::ZipCode { zip, country } => {
+            Ok(Resolve::Zip(geocoding_zip(zip, country)))
+        }
+        // There is no geocoding by city ID, the current weather carries the coordinates
+        LocationSpecifier::CityId(_) => Ok(Resolve::Weather(current_weather(location)?)),
+        _ => Err(Error::Input {
+            msg: format!("{:?} does not specify a single location", location),
+        }),
+    }
+}
+
+pub(crate) fn first_match(
+    location: &LocationSpecifier,
+    mut found: Vec<GeocodingLocation>,
+) -> Result<Coordinates> {
+    if found.is_empty() {
+        return Err(Error::Input {
+            msg: format!("No geocoding match for {:?}", location),
+        });
+    }
+    Ok(found.swap_remove(0).coord)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_date, format_utc_offset};
+    use time::{Date, Month, UtcOffset};
+
+    #[test]
+    fn formats_dates_and_offsets() {
+        let date = Date::from_calendar_date(2020, Month::March, 4).unwrap();
+        assert_eq!(format_date(date), "2020-03-04");
+
+        let offset = |hours, minutes| UtcOffset::from_hms(hours, minutes, 0).unwrap();
+        assert_eq!(format_utc_offset(offset(0, 0)).unwrap(), "+00:00");
+        assert_eq!(format_utc_offset(offset(-5, 0)).unwrap(), "-05:00");
+        assert_eq!(format_utc_offset(offset(5, 30)).unwrap(), "+05:30");
+        assert!(format_utc_offset(offset(15, 0)).is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use crate::Error;
+
+/// How a client retries requests failing with an error for which
+/// `Error::is_retryable` holds: rate limiting, server errors and failed
+/// connections.
+///
+/// The delay before retry `n` is `base_delay * 2^(n - 1)`, capped at
+/// `max_delay`. With jitter a random delay between zero and that value is
+/// used instead, so many clients failing at once do not retry in lockstep.
+/// A `Retry-After` sent by the API takes precedence, still capped at
+/// `max_delay`.
+///
+/// ```
+/// use std::time::Duration;
+/// use openweather::RetryPolicy;
+///
+/// let policy = RetryPolicy::new(4)
+///     .base_delay(Duration::from_millis(200))
+///     .max_delay(Duration::from_secs(5));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Sends every request at most `max_attempts` times, waiting 500 ms
+    /// before the first retry and at most 30 s between attempts, with
+    /// jitter.
+    pub fn new(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+
+    /// Sends every request once, the default.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::new(1)
+    }
+
+    pub fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// How long to wait before sending attempt `attempt + 1` after attempt
+    /// `attempt` failed with `error`, `None` to give up.
+    pub(crate) fn delay(&self, attempt: u32, error: &Error) -> Option<Duration> {
+        if attempt >= self.max_attempts || !error.is_retryable() {
+            return None;
+        }
+        if let Some(retry_after) = error.retry_after() {
+            return Some(retry_after.min(self.max_delay));
+        }
+        let backoff = self
+            .base_delay
+            .checked_mul(1 << (attempt - 1).min(31))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        if self.jitter {
+            Some(backoff.mul_f64(random_fraction()))
+        } else {
+            Some(backoff)
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::none()
+    }
+}
+
+/// A random number in `[0, 1)`, from the randomly seeded std hasher.
+fn random_fraction() -> f64 {
+    let random = RandomState::new().build_hasher().finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{ApiError, Error, RetryPolicy};
+
+    fn api_error(status: u16, retry_after: Option<Duration>) -> Error {
+        Error::Api(ApiError {
+            status,
+            report: None,
+            body: String::new(),
+            retry_after,
+        })
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_cap() {
+        let policy = RetryPolicy::new(6)
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5))
+            .jitter(false);
+        let err = api_error(503, None);
+        let delays: Vec<_> = (1..=6).map(|attempt| policy.delay(attempt, &err)).collect();
+        assert_eq!(
+            delays,
+            vec![
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(2)),
+                Some(Duration::from_secs(4)),
+                Some(Duration::from_secs(5)),
+                Some(Duration::from_secs(5)),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn honors_retry_after_and_skips_permanent_errors() {
+        let policy = RetryPolicy::new(3).max_delay(Duration::from_secs(10));
+        let err = api_error(429, Some(Duration::from_secs(7)));
+        assert_eq!(policy.delay(1, &err), Some(Duration::from_secs(7)));
+        assert_eq!(policy.delay(1, &api_error(401, None)), None);
+
+        let jittered = policy.delay(2, &api_error(500, None)).unwrap();
+        assert!(jittered <= Duration::from_secs(1));
+    }
+}
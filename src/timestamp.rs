@@ -0,0 +1,279 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
+use std::time::{Duration, SystemTime};
+
+use serde::de::{Deserialize, Deserializer, Error as _};
+use serde::ser::{Serialize, Serializer};
+use time::error::ConversionRange;
+use time::{OffsetDateTime, PrimitiveDateTime, UtcOffset};
+
+use crate::weather_types::*;
+
+/// A point in time, sent to and received from the API as Unix seconds.
+///
+/// Derefs to the UTC `OffsetDateTime` and converts from `OffsetDateTime`,
+/// `SystemTime` and, with the `chrono` feature, `chrono::DateTime`, so any
+/// of them can be passed where a request takes `impl Into<Timestamp>`. Times
+/// outside the range of `OffsetDateTime` are clamped to it. Defaults to the
+/// Unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(OffsetDateTime);
+
+impl Timestamp {
+    /// The timestamp `seconds` after the Unix epoch, `None` when out of the
+    /// range `OffsetDateTime` supports.
+    pub fn from_unix(seconds: i64) -> Option<Timestamp> {
+        OffsetDateTime::from_unix_timestamp(seconds)
+            .ok()
+            .map(Timestamp)
+    }
+
+    pub fn unix(&self) -> i64 {
+        self.0.unix_timestamp()
+    }
+
+    pub fn utc(&self) -> OffsetDateTime {
+        self.0
+    }
+
+    /// The local time at a place `offset` away from UTC, e.g. the
+    /// `utc_offset` of the report the timestamp is part of.
+    pub fn local(&self, offset: UtcOffset) -> OffsetDateTime {
+        self.0.to_offset(offset)
+    }
+
+    /// The timestamp as a `chrono::DateTime`, clamped to the range chrono
+    /// supports.
+    #[cfg(feature = "chrono")]
+    pub fn chrono(&self) -> chrono::DateTime<chrono::Utc> {
+        use chrono::{DateTime, TimeZone, Utc};
+        Utc.timestamp_opt(self.0.unix_timestamp(), self.0.nanosecond())
+            .single()
+            .unwrap_or(if self.0.unix_timestamp() < 0 {
+                DateTime::<Utc>::MIN_UTC
+            } else {
+                DateTime::<Utc>::MAX_UTC
+            })
+    }
+
+    /// The timestamp `nanos` after the Unix epoch, clamped to the range of
+    /// `OffsetDateTime`.
+    fn from_unix_nanos(nanos: i128) -> Timestamp {
+        let time = OffsetDateTime::from_unix_timestamp_nanos(nanos).unwrap_or_else(|_| {
+            if nanos < 0 {
+                PrimitiveDateTime::MIN.assume_utc()
+            } else {
+                PrimitiveDateTime::MAX.assume_utc()
+            }
+        });
+        Timestamp(time)
+    }
+}
+
+impl Default for Timestamp {
+    fn default() -> Self {
+        Timestamp(OffsetDateTime::UNIX_EPOCH)
+    }
+}
+
+impl Deref for Timestamp {
+    type Target = OffsetDateTime;
+
+    fn deref(&self) -> &OffsetDateTime {
+        &self.0
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<OffsetDateTime> for Timestamp {
+    fn from(time: OffsetDateTime) -> Timestamp {
+        Timestamp(time.to_offset(UtcOffset::UTC))
+    }
+}
+
+impl From<Timestamp> for OffsetDateTime {
+    fn from(timestamp: Timestamp) -> OffsetDateTime {
+        timestamp.0
+    }
+}
+
+/// Fails on platforms whose `SystemTime` does not reach the timestamp.
+impl TryFrom<Timestamp> for SystemTime {
+    type Error = ConversionRange;
+
+    fn try_from(timestamp: Timestamp) -> Result<SystemTime, ConversionRange> {
+        let nanos = timestamp.0.unix_timestamp_nanos();
+        let since_epoch = Duration::new(
+            (nanos.unsigned_abs() / 1_000_000_000) as u64,
+            (nanos.unsigned_abs() % 1_000_000_000) as u32,
+        );
+        let time = if nanos < 0 {
+            SystemTime::UNIX_EPOCH.checked_sub(since_epoch)
+        } else {
+            SystemTime::UNIX_EPOCH.checked_add(since_epoch)
+        };
+        time.ok_or(ConversionRange)
+    }
+}
+
+impl From<SystemTime> for Timestamp {
+    fn from(time: SystemTime) -> Timestamp {
+        let nanos = match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(after) => i128::try_from(after.as_nanos()).unwrap_or(i128::MAX),
+            Err(before) => {
+                i128::try_from(before.duration().as_nanos()).map_or(i128::MIN, |nanos| -nanos)
+            }
+        };
+        Timestamp::from_unix_nanos(nanos)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<Tz: chrono::TimeZone> From<chrono::DateTime<Tz>> for Timestamp {
+    fn from(time: chrono::DateTime<Tz>) -> Timestamp {
+        let nanos = i128::from(time.timestamp()) * 1_000_000_000
+            + i128::from(time.timestamp_subsec_nanos());
+        Timestamp::from_unix_nanos(nanos)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(self.unix())
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let seconds = i64::deserialize(deserializer)?;
+        Timestamp::from_unix(seconds)
+            .ok_or_else(|| D::Error::custom(format!("timestamp {} out of range", seconds)))
+    }
+}
+
+/// The offset of a report's timezone, given in seconds east of UTC.
+fn utc_offset(seconds: i64) -> UtcOffset {
+    i32::try_from(seconds)
+        .ok()
+        .and_then(|seconds| UtcOffset::from_whole_seconds(seconds).ok())
+        .unwrap_or(UtcOffset::UTC)
+}
+
+impl WeatherReportCurrent {
+    /// The UTC offset of the city, UTC when the report has none.
+    pub fn utc_offset(&self) -> UtcOffset {
+        utc_offset(self.timezone.unwrap_or(0).into())
+    }
+
+    pub fn local_sunrise(&self) -> OffsetDateTime {
+        self.sys.sunrise.local(self.utc_offset())
+    }
+
+    pub fn local_sunset(&self) -> OffsetDateTime {
+        self.sys.sunset.local(self.utc_offset())
+    }
+}
+
+impl WeatherReport5Day {
+    pub fn utc_offset(&self) -> UtcOffset {
+        utc_offset(self.city.timezone.into())
+    }
+
+    pub fn local_sunrise(&self) -> OffsetDateTime {
+        self.city.sunrise.local(self.utc_offset())
+    }
+
+    pub fn local_sunset(&self) -> OffsetDateTime {
+        self.city.sunset.local(self.utc_offset())
+    }
+}
+
+impl WeatherReportOneCall {
+    pub fn utc_offset(&self) -> UtcOffset {
+        utc_offset(self.timezone_offset)
+    }
+
+    pub fn local_sunrise(&self) -> OffsetDateTime {
+        self.current.sunrise.local(self.utc_offset())
+    }
+
+    pub fn local_sunset(&self) -> OffsetDateTime {
+        self.current.sunset.local(self.utc_offset())
+    }
+}
+
+impl WeatherReportOneCall3 {
+    pub fn utc_offset(&self) -> UtcOffset {
+        utc_offset(self.timezone_offset)
+    }
+
+    /// `None` when the current block was not requested.
+    pub fn local_sunrise(&self) -> Option<OffsetDateTime> {
+        let current = self.current.as_ref()?;
+        Some(current.sunrise.local(self.utc_offset()))
+    }
+
+    /// `None` when the current block was not requested.
+    pub fn local_sunset(&self) -> Option<OffsetDateTime> {
+        let current = self.current.as_ref()?;
+        Some(current.sunset.local(self.utc_offset()))
+    }
+}
+
+impl WeatherReportOneCallHistorical {
+    pub fn utc_offset(&self) -> UtcOffset {
+        utc_offset(self.timezone_offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+    use std::time::{Duration, SystemTime};
+
+    use super::Timestamp;
+    use time::{Month, UtcOffset};
+
+    #[test]
+    fn round_trips_unix_seconds() {
+        let sunrise: Timestamp = serde_json::from_str("1591006321").unwrap();
+        assert_eq!(sunrise.unix(), 1591006321);
+        assert_eq!(sunrise.month(), Month::June);
+        assert_eq!(serde_json::to_string(&sunrise).unwrap(), "1591006321");
+
+        let local = sunrise.local(UtcOffset::from_hms(-5, 0, 0).unwrap());
+        assert_eq!((local.hour(), local.minute()), (5, 12));
+    }
+
+    #[test]
+    fn clamps_out_of_range_times() {
+        let far = Duration::from_secs(400_000 * 365 * 24 * 60 * 60);
+        if let Some(future) = SystemTime::UNIX_EPOCH.checked_add(far) {
+            assert_eq!(Timestamp::from(future).year(), 9999);
+        }
+        if let Some(past) = SystemTime::UNIX_EPOCH.checked_sub(far) {
+            assert_eq!(Timestamp::from(past).year(), -9999);
+        }
+
+        let sunrise = Timestamp::from_unix(1591006321).unwrap();
+        let time = SystemTime::try_from(sunrise).unwrap();
+        assert_eq!(Timestamp::from(time), sunrise);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn clamps_chrono_times() {
+        use chrono::{DateTime, Utc};
+
+        assert_eq!(Timestamp::from(DateTime::<Utc>::MAX_UTC).year(), 9999);
+        assert_eq!(Timestamp::from(DateTime::<Utc>::MIN_UTC).year(), -9999);
+        let sunrise = Timestamp::from_unix(1591006321).unwrap();
+        assert_eq!(Timestamp::from(sunrise.chrono()), sunrise);
+    }
+}
@@ -0,0 +1,147 @@
+use std::future::Future;
+use std::pin::Pin;
+#[cfg(feature = "blocking")]
+use std::time::Duration;
+
+use url::Url;
+
+use crate::Result;
+
+/// A boxed future as returned by `AsyncTransport`.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// The raw answer to a request, before it is parsed into a report.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Looks up a header by its case-insensitive name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Performs the HTTP GET requests of a blocking `Client`.
+///
+/// Implement this to route requests through another HTTP library or to
+/// answer them without a network, see `MockTransport`. Transports are shared
+/// with the threads revalidating `DiskCache` entries in the background.
+pub trait Transport: Send + Sync + 'static {
+    fn get(&self, url: &Url) -> Result<HttpResponse>;
+}
+
+/// Performs the HTTP GET requests of an `AsyncClient`.
+pub trait AsyncTransport: Send + Sync + 'static {
+    fn get<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, Result<HttpResponse>>;
+}
+
+/// The default blocking transport, built on `http_req`.
+#[cfg(feature = "blocking")]
+#[derive(Debug, Clone)]
+pub struct HttpReqTransport {
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+}
+
+#[cfg(feature = "blocking")]
+impl HttpReqTransport {
+    pub fn new(connect_timeout: Option<Duration>, read_timeout: Option<Duration>) -> Self {
+        HttpReqTransport {
+            connect_timeout,
+            read_timeout,
+        }
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Default for HttpReqTransport {
+    fn default() -> Self {
+        HttpReqTransport::new(Some(Duration::from_secs(60)), Some(Duration::from_secs(60)))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Transport for HttpReqTransport {
+    fn get(&self, url: &Url) -> Result<HttpResponse> {
+        let uri = url.as_str().parse()?;
+        let mut body = Vec::new();
+
+        let res = http_req::request::Request::new(&uri)
+            .connect_timeout(self.connect_timeout)
+            .read_timeout(self.read_timeout)
+            .send(&mut body)?;
+        Ok(HttpResponse {
+            status: res.status_code().into(),
+            headers: res
+                .headers()
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+            body,
+        })
+    }
+}
+
+/// The default async transport, built on `reqwest`.
+#[cfg(feature = "async")]
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestTransport {
+    http: reqwest::Client,
+}
+
+#[cfg(feature = "async")]
+impl ReqwestTransport {
+    pub fn new(
+        connect_timeout: Option<std::time::Duration>,
+        read_timeout: Option<std::time::Duration>,
+    ) -> Result<Self> {
+        let mut http = reqwest::Client::builder();
+        if let Some(timeout) = connect_timeout {
+            http = http.connect_timeout(timeout);
+        }
+        if let Some(timeout) = read_timeout {
+            http = http.read_timeout(timeout);
+        }
+        Ok(ReqwestTransport {
+            http: http.build()?,
+        })
+    }
+}
+
+#[cfg(feature = "async")]
+impl From<reqwest::Client> for ReqwestTransport {
+    fn from(http: reqwest::Client) -> Self {
+        ReqwestTransport { http }
+    }
+}
+
+#[cfg(feature = "async")]
+impl AsyncTransport for ReqwestTransport {
+    fn get<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, Result<HttpResponse>> {
+        Box::pin(async move {
+            let res = self.http.get(url.as_str()).send().await?;
+            let status = res.status().as_u16();
+            let headers = res
+                .headers()
+                .iter()
+                .map(|(key, value)| {
+                    let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+                    (key.to_string(), value)
+                })
+                .collect();
+            let body = res.bytes().await?.to_vec();
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        })
+    }
+}
@@ -0,0 +1,655 @@
+use crate::weather_types::*;
+use crate::Unit;
+
+/// A temperature scale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TemperatureScale {
+    Kelvin,
+    Celsius,
+    Fahrenheit,
+}
+
+/// A unit of wind speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpeedUnit {
+    MetersPerSecond,
+    KilometersPerHour,
+    MilesPerHour,
+    Knots,
+}
+
+/// A unit of atmospheric pressure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PressureUnit {
+    Hectopascal,
+    InchesOfMercury,
+    MillimetersOfMercury,
+}
+
+/// A unit of precipitation depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LengthUnit {
+    Millimeters,
+    Inches,
+}
+
+impl Unit {
+    /// The scale temperatures are reported in.
+    pub fn temperature_scale(self) -> TemperatureScale {
+        match self {
+            Unit::Standard => TemperatureScale::Kelvin,
+            Unit::Metric => TemperatureScale::Celsius,
+            Unit::Imperial => TemperatureScale::Fahrenheit,
+        }
+    }
+
+    /// The unit wind speeds are reported in.
+    pub fn speed_unit(self) -> SpeedUnit {
+        match self {
+            Unit::Standard | Unit::Metric => SpeedUnit::MetersPerSecond,
+            Unit::Imperial => SpeedUnit::MilesPerHour,
+        }
+    }
+}
+
+/// A temperature keeping the scale it was given in, so reading it back in
+/// that scale returns the original value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Temperature {
+    value: f64,
+    scale: TemperatureScale,
+}
+
+impl Temperature {
+    pub fn new(value: f64, scale: TemperatureScale) -> Temperature {
+        Temperature { value, scale }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn scale(&self) -> TemperatureScale {
+        self.scale
+    }
+
+    /// The temperature in `scale`.
+    pub fn get(&self, scale: TemperatureScale) -> f64 {
+        if scale == self.scale {
+            return self.value;
+        }
+        let kelvin = match self.scale {
+            TemperatureScale::Kelvin => self.value,
+            TemperatureScale::Celsius => self.value + 273.15,
+            TemperatureScale::Fahrenheit => (self.value - 32.0) * 5.0 / 9.0 + 273.15,
+        };
+        match scale {
+            TemperatureScale::Kelvin => kelvin,
+            TemperatureScale::Celsius => kelvin - 273.15,
+            TemperatureScale::Fahrenheit => (kelvin - 273.15) * 9.0 / 5.0 + 32.0,
+        }
+    }
+
+    pub fn to(&self, scale: TemperatureScale) -> Temperature {
+        Temperature::new(self.get(scale), scale)
+    }
+
+    pub fn kelvin(&self) -> f64 {
+        self.get(TemperatureScale::Kelvin)
+    }
+
+    pub fn celsius(&self) -> f64 {
+        self.get(TemperatureScale::Celsius)
+    }
+
+    pub fn fahrenheit(&self) -> f64 {
+        self.get(TemperatureScale::Fahrenheit)
+    }
+}
+
+impl SpeedUnit {
+    fn meters_per_second(self) -> f64 {
+        match self {
+            SpeedUnit::MetersPerSecond => 1.0,
+            SpeedUnit::KilometersPerHour => 1000.0 / 3600.0,
+            SpeedUnit::MilesPerHour => 0.44704,
+            SpeedUnit::Knots => 1852.0 / 3600.0,
+        }
+    }
+}
+
+/// A speed keeping the unit it was given in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Speed {
+    value: f64,
+    unit: SpeedUnit,
+}
+
+impl Speed {
+    pub fn new(value: f64, unit: SpeedUnit) -> Speed {
+        Speed { value, unit }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn unit(&self) -> SpeedUnit {
+        self.unit
+    }
+
+    /// The speed in `unit`.
+    pub fn get(&self, unit: SpeedUnit) -> f64 {
+        if unit == self.unit {
+            return self.value;
+        }
+        self.value * self.unit.meters_per_second() / unit.meters_per_second()
+    }
+
+    pub fn to(&self, unit: SpeedUnit) -> Speed {
+        Speed::new(self.get(unit), unit)
+    }
+}
+
+impl PressureUnit {
+    fn hectopascal(self) -> f64 {
+        match self {
+            PressureUnit::Hectopascal => 1.0,
+            PressureUnit::InchesOfMercury => 33.863_886_666_667,
+            PressureUnit::MillimetersOfMercury => 1.333_223_874_15,
+        }
+    }
+}
+
+/// An atmospheric pressure keeping the unit it was given in. The API always
+/// reports pressure in hPa.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pressure {
+    value: f64,
+    unit: PressureUnit,
+}
+
+impl Pressure {
+    pub fn new(value: f64, unit: PressureUnit) -> Pressure {
+        Pressure { value, unit }
+    }
+
+    pub fn hectopascal(value: f64) -> Pressure {
+        Pressure::new(value, PressureUnit::Hectopascal)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn unit(&self) -> PressureUnit {
+        self.unit
+    }
+
+    /// The pressure in `unit`.
+    pub fn get(&self, unit: PressureUnit) -> f64 {
+        if unit == self.unit {
+            return self.value;
+        }
+        self.value * self.unit.hectopascal() / unit.hectopascal()
+    }
+
+    pub fn to(&self, unit: PressureUnit) -> Pressure {
+        Pressure::new(self.get(unit), unit)
+    }
+}
+
+impl LengthUnit {
+    fn millimeters(self) -> f64 {
+        match self {
+            LengthUnit::Millimeters => 1.0,
+            LengthUnit::Inches => 25.4,
+        }
+    }
+}
+
+/// A precipitation depth keeping the unit it was given in. The API always
+/// reports precipitation in mm.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Length {
+    value: f64,
+    unit: LengthUnit,
+}
+
+impl Length {
+    pub fn new(value: f64, unit: LengthUnit) -> Length {
+        Length { value, unit }
+    }
+
+    pub fn millimeters(value: f64) -> Length {
+        Length::new(value, LengthUnit::Millimeters)
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn unit(&self) -> LengthUnit {
+        self.unit
+    }
+
+    /// The depth in `unit`.
+    pub fn get(&self, unit: LengthUnit) -> f64 {
+        if unit == self.unit {
+            return self.value;
+        }
+        self.value * self.unit.millimeters() / unit.millimeters()
+    }
+
+    pub fn to(&self, unit: LengthUnit) -> Length {
+        Length::new(self.get(unit), unit)
+    }
+}
+
+/// A part of a report together with the `unit` of the report, which its raw
+/// fields only make sense with. Dereferences to the part.
+///
+/// Reports hand out their parts through `measured`:
+///
+/// ```
+/// use openweather::{TemperatureScale, Unit, WeatherReportCurrent};
+///
+/// let mut report = WeatherReportCurrent::default();
+/// report.unit = Unit::Metric;
+/// report.main.temp = 20.0;
+/// let temperature = report.measured().main().temperature();
+/// assert_eq!(temperature.scale(), TemperatureScale::Celsius);
+/// assert!((temperature.kelvin() - 293.15).abs() < 1e-9);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measured<'a, T> {
+    part: &'a T,
+    unit: Unit,
+}
+
+impl<'a, T> Measured<'a, T> {
+    /// The unit the raw fields of the part are in.
+    pub fn unit(&self) -> Unit {
+        self.unit
+    }
+
+    pub fn get(&self) -> &'a T {
+        self.part
+    }
+
+    fn of<U>(&self, part: &'a U) -> Measured<'a, U> {
+        Measured {
+            part,
+            unit: self.unit,
+        }
+    }
+
+    fn temperature_of(&self, value: f32) -> Temperature {
+        Temperature::new(value.into(), self.unit.temperature_scale())
+    }
+
+    fn speed_of(&self, value: f32) -> Speed {
+        Speed::new(value.into(), self.unit.speed_unit())
+    }
+}
+
+impl<T> std::ops::Deref for Measured<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.part
+    }
+}
+
+macro_rules! measured_reports {
+    ($($report:ty),*) => {
+        $(impl $report {
+            /// The report together with its `unit`, for typed temperatures
+            /// and speeds.
+            pub fn measured(&self) -> Measured<'_, Self> {
+                Measured {
+                    part: self,
+                    unit: self.unit,
+                }
+            }
+        })*
+    };
+}
+
+measured_reports!(
+    WeatherReportCurrent,
+    WeatherReportCities,
+    WeatherReport5Day,
+    WeatherReport16Day,
+    WeatherReportHistorical,
+    WeatherReportOneCall,
+    WeatherReportOneCall3,
+    WeatherReportOneCallHistorical
+);
+
+impl<'a> Measured<'a, WeatherReportCurrent> {
+    pub fn main(&self) -> Measured<'a, Main> {
+        self.of(&self.part.main)
+    }
+
+    pub fn wind(&self) -> Measured<'a, Wind> {
+        self.of(&self.part.wind)
+    }
+}
+
+impl<'a> Measured<'a, WeatherReportCities> {
+    pub fn list(&self) -> impl Iterator<Item = Measured<'a, CityWeather>> + 'a {
+        let unit = self.unit;
+        self.part
+            .list
+            .iter()
+            .map(move |part| Measured { part, unit })
+    }
+}
+
+impl<'a> Measured<'a, CityWeather> {
+    pub fn main(&self) -> Measured<'a, Main> {
+        self.of(&self.part.main)
+    }
+
+    pub fn wind(&self) -> Measured<'a, Wind> {
+        self.of(&self.part.wind)
+    }
+}
+
+impl<'a> Measured<'a, WeatherReport5Day> {
+    pub fn list(&self) -> impl Iterator<Item = Measured<'a, TimeSliceHourly>> + 'a {
+        let unit = self.unit;
+        self.part
+            .list
+            .iter()
+            .map(move |part| Measured { part, unit })
+    }
+}
+
+impl<'a> Measured<'a, TimeSliceHourly> {
+    pub fn main(&self) -> Measured<'a, Main> {
+        self.of(&self.part.main)
+    }
+
+    pub fn wind(&self) -> Measured<'a, Wind> {
+        self.of(&self.part.wind)
+    }
+}
+
+impl<'a> Measured<'a, WeatherReport16Day> {
+    pub fn list(&self) -> impl Iterator<Item = Measured<'a, TimeSliceDaily>> + 'a {
+        let unit = self.unit;
+        self.part
+            .list
+            .iter()
+            .map(move |part| Measured { part, unit })
+    }
+}
+
+impl<'a> Measured<'a, WeatherReportHistorical> {
+    pub fn list(&self) -> impl Iterator<Item = Measured<'a, WeatherReportHistoricalElement>> + 'a {
+        let unit = self.unit;
+        self.part
+            .list
+            .iter()
+            .map(move |part| Measured { part, unit })
+    }
+}
+
+impl<'a> Measured<'a, WeatherReportHistoricalElement> {
+    pub fn main(&self) -> Measured<'a, Main> {
+        self.of(&self.part.main)
+    }
+
+    pub fn wind(&self) -> Measured<'a, Wind> {
+        self.of(&self.part.wind)
+    }
+}
+
+impl<'a> Measured<'a, WeatherReportOneCall> {
+    pub fn current(&self) -> Measured<'a, WeatherReportOneCallCurrent> {
+        self.of(&self.part.current)
+    }
+
+    pub fn daily(&self) -> impl Iterator<Item = Measured<'a, WeatherReportOneCallDaily>> + 'a {
+        let unit = self.unit;
+        self.part
+            .daily
+            .iter()
+            .map(move |part| Measured { part, unit })
+    }
+}
+
+impl<'a> Measured<'a, WeatherReportOneCall3> {
+    pub fn current(&self) -> Option<Measured<'a, WeatherReportOneCallCurrent>> {
+        self.part.current.as_ref().map(|part| self.of(part))
+    }
+
+    pub fn hourly(&self) -> impl Iterator<Item = Measured<'a, WeatherReportOneCallHourly>> + 'a {
+        let unit = self.unit;
+        self.part
+            .hourly
+            .iter()
+            .flatten()
+            .map(move |part| Measured { part, unit })
+    }
+
+    pub fn daily(&self) -> impl Iterator<Item = Measured<'a, WeatherReportOneCallDaily>> + 'a {
+        let unit = self.unit;
+        self.part
+            .daily
+            .iter()
+            .flatten()
+            .map(move |part| Measured { part, unit })
+    }
+}
+
+impl<'a> Measured<'a, WeatherReportOneCallHistorical> {
+    pub fn current(&self) -> Measured<'a, WeatherReportOneCallCurrent> {
+        self.of(&self.part.current)
+    }
+
+    pub fn hourly(&self) -> impl Iterator<Item = Measured<'a, WeatherReportOneCallHourly>> + 'a {
+        let unit = self.unit;
+        self.part
+            .hourly
+            .iter()
+            .map(move |part| Measured { part, unit })
+    }
+}
+
+impl Main {
+    pub fn pressure(&self) -> Pressure {
+        Pressure::hectopascal(self.pressure.into())
+    }
+}
+
+impl Measured<'_, Main> {
+    pub fn temperature(&self) -> Temperature {
+        self.temperature_of(self.part.temp)
+    }
+
+    pub fn temperature_min(&self) -> Temperature {
+        self.temperature_of(self.part.temp_min)
+    }
+
+    pub fn temperature_max(&self) -> Temperature {
+        self.temperature_of(self.part.temp_max)
+    }
+}
+
+impl Measured<'_, Wind> {
+    pub fn wind_speed(&self) -> Speed {
+        self.speed_of(self.part.speed)
+    }
+
+    pub fn wind_gust(&self) -> Option<Speed> {
+        self.part.gust.map(|gust| self.speed_of(gust))
+    }
+}
+
+impl Rain {
+    pub fn last_hour(&self) -> Option<Length> {
+        self.one_h.map(|mm| Length::millimeters(mm.into()))
+    }
+
+    pub fn last_3_hours(&self) -> Option<Length> {
+        self.three_h.map(|mm| Length::millimeters(mm.into()))
+    }
+}
+
+impl Snow {
+    pub fn last_hour(&self) -> Option<Length> {
+        self.one_h.map(|mm| Length::millimeters(mm.into()))
+    }
+
+    pub fn last_3_hours(&self) -> Option<Length> {
+        self.three_h.map(|mm| Length::millimeters(mm.into()))
+    }
+}
+
+impl WeatherReportOneCallCurrent {
+    pub fn pressure(&self) -> Pressure {
+        Pressure::hectopascal(self.pressure as f64)
+    }
+}
+
+impl Measured<'_, WeatherReportOneCallCurrent> {
+    pub fn temperature(&self) -> Temperature {
+        self.temperature_of(self.part.temp)
+    }
+
+    pub fn feels_like_temperature(&self) -> Temperature {
+        self.temperature_of(self.part.feels_like)
+    }
+
+    pub fn dew_point_temperature(&self) -> Temperature {
+        self.temperature_of(self.part.dew_point)
+    }
+
+    pub fn wind_speed(&self) -> Speed {
+        self.speed_of(self.part.wind_speed)
+    }
+}
+
+impl WeatherReportOneCallHourly {
+    pub fn pressure(&self) -> Pressure {
+        Pressure::hectopascal(self.pressure as f64)
+    }
+}
+
+impl Measured<'_, WeatherReportOneCallHourly> {
+    pub fn temperature(&self) -> Temperature {
+        self.temperature_of(self.part.temp)
+    }
+
+    pub fn feels_like_temperature(&self) -> Temperature {
+        self.temperature_of(self.part.feels_like)
+    }
+
+    pub fn wind_speed(&self) -> Speed {
+        self.speed_of(self.part.wind_speed)
+    }
+}
+
+impl WeatherReportOneCallDaily {
+    pub fn pressure(&self) -> Pressure {
+        Pressure::hectopascal(self.pressure as f64)
+    }
+
+    pub fn rain_total(&self) -> Option<Length> {
+        self.rain.map(|mm| Length::millimeters(mm.into()))
+    }
+
+    pub fn snow_total(&self) -> Option<Length> {
+        self.snow.map(|mm| Length::millimeters(mm.into()))
+    }
+}
+
+impl Measured<'_, WeatherReportOneCallDaily> {
+    pub fn temperature_min(&self) -> Temperature {
+        self.temperature_of(self.part.temp.min)
+    }
+
+    pub fn temperature_max(&self) -> Temperature {
+        self.temperature_of(self.part.temp.max)
+    }
+
+    pub fn wind_speed(&self) -> Speed {
+        self.speed_of(self.part.wind_speed)
+    }
+}
+
+impl TimeSliceDaily {
+    pub fn pressure(&self) -> Pressure {
+        Pressure::hectopascal(self.pressure.into())
+    }
+}
+
+impl Measured<'_, TimeSliceDaily> {
+    pub fn temperature_min(&self) -> Temperature {
+        self.temperature_of(self.part.temp.min)
+    }
+
+    pub fn temperature_max(&self) -> Temperature {
+        self.temperature_of(self.part.temp.max)
+    }
+
+    pub fn wind_speed(&self) -> Speed {
+        self.speed_of(self.part.speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Length, LengthUnit, Main, Pressure, PressureUnit, Speed, SpeedUnit, Temperature,
+        TemperatureScale, WeatherReportCurrent,
+    };
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn converts_temperatures() {
+        let t = Temperature::new(20.0, TemperatureScale::Celsius);
+        assert!(close(t.kelvin(), 293.15));
+        assert!(close(t.fahrenheit(), 68.0));
+        assert!(close(t.to(TemperatureScale::Fahrenheit).celsius(), 20.0));
+        assert_eq!(t.celsius(), 20.0);
+
+        let report = WeatherReportCurrent {
+            main: Main {
+                temp: 291.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let t = report.measured().main().temperature();
+        assert_eq!(t.scale(), TemperatureScale::Kelvin);
+        assert_eq!(t.kelvin(), 291.5);
+    }
+
+    #[test]
+    fn converts_speed_pressure_and_length() {
+        let s = Speed::new(10.0, SpeedUnit::MetersPerSecond);
+        assert!(close(s.get(SpeedUnit::KilometersPerHour), 36.0));
+        assert!(close(
+            s.to(SpeedUnit::Knots).get(SpeedUnit::MetersPerSecond),
+            10.0
+        ));
+        assert!(close(
+            Speed::new(1.0, SpeedUnit::MilesPerHour).get(SpeedUnit::MetersPerSecond),
+            0.44704
+        ));
+
+        let p = Pressure::hectopascal(1013.25);
+        assert!((p.get(PressureUnit::InchesOfMercury) - 29.92).abs() < 0.01);
+        assert!((p.get(PressureUnit::MillimetersOfMercury) - 760.0).abs() < 0.01);
+
+        let l = Length::new(1.0, LengthUnit::Inches);
+        assert!(close(l.get(LengthUnit::Millimeters), 25.4));
+    }
+}
@@ -1,8 +1,16 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
 use serde_derive::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+use crate::{Timestamp, Unit};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct Coordinates {
+    // `box/city` capitalizes the coordinates
+    #[serde(alias = "Lat")]
     pub lat: f32,
+    #[serde(alias = "Lon")]
     pub lon: f32,
 }
 
@@ -14,8 +22,8 @@ pub struct CityShort {
     pub country: String,
     pub population: u32,
     pub timezone: i32,
-    pub sunrise: u64,
-    pub sunset: u64,
+    pub sunrise: Timestamp,
+    pub sunset: Timestamp,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
@@ -46,6 +54,8 @@ pub struct Main {
     pub temp_kf: Option<f32>,
 }
 
+/// A weather condition as sent by the API, see `Weather::condition` for the
+/// typed code.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct Weather {
     pub id: u32,
@@ -56,6 +66,7 @@ pub struct Weather {
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct Clouds {
+    #[serde(alias = "today")]
     pub all: i32,
 }
 
@@ -71,6 +82,8 @@ pub struct Rain {
     /// Rain volume in mm
     #[serde(rename = "3h")]
     pub three_h: Option<f32>,
+    #[serde(rename = "1h")]
+    pub one_h: Option<f32>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
@@ -99,7 +112,7 @@ pub struct TempDaily {
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct TimeSliceHourly {
-    pub dt: u64,
+    pub dt: Timestamp,
     pub main: Main,
     pub weather: Vec<Weather>,
     pub clouds: Clouds,
@@ -112,7 +125,7 @@ pub struct TimeSliceHourly {
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct TimeSliceDaily {
-    pub dt: u64,
+    pub dt: Timestamp,
     pub temp: TempDaily,
     pub pressure: f32,
     pub humidity: f32,
@@ -124,10 +137,29 @@ pub struct TimeSliceDaily {
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct ErrorReport {
+    /// Sent as a number or, by some endpoints, as a string
+    #[serde(deserialize_with = "number_or_string")]
     pub cod: u32,
+    #[serde(default)]
     pub message: String,
 }
 
+fn number_or_string<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Cod {
+        Number(u32),
+        String(String),
+    }
+    match serde::Deserialize::deserialize(deserializer)? {
+        Cod::Number(cod) => Ok(cod),
+        Cod::String(cod) => cod.parse().map_err(serde::de::Error::custom),
+    }
+}
+
 use core::fmt;
 impl fmt::Display for ErrorReport {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -142,15 +174,15 @@ pub struct Sys {
     pub id: u32,
     pub message: Option<f32>,
     pub country: String,
-    pub sunrise: u64,
-    pub sunset: u64,
+    pub sunrise: Timestamp,
+    pub sunset: Timestamp,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct WeatherReportOneCallCurrent {
-    pub dt: u64,
-    pub sunrise: u64,
-    pub sunset: u64,
+    pub dt: Timestamp,
+    pub sunrise: Timestamp,
+    pub sunset: Timestamp,
     pub temp: f32,
     pub feels_like: f32,
     pub pressure: u64,
@@ -161,7 +193,9 @@ pub struct WeatherReportOneCallCurrent {
     pub visibility: Option<u64>,
     pub wind_speed: f32,
     pub wind_deg: u64,
+    pub wind_gust: Option<f32>,
     pub weather: Vec<Weather>,
+    pub rain: Option<Rain>,
     pub snow: Option<Snow>,
 }
 
@@ -183,28 +217,31 @@ pub struct WeatherReportOneCallFeelsLike {
     pub morn: f32,
 }
 
-
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct WeatherReportOneCallDaily {
-    pub dt: u64,
-    pub sunrise: u64,
-    pub sunset: u64,
+    pub dt: Timestamp,
+    pub sunrise: Timestamp,
+    pub sunset: Timestamp,
     pub temp: WeatherReportOneCallTemp,
+    pub feels_like: Option<WeatherReportOneCallFeelsLike>,
     pub pressure: u64,
     pub humidity: u64,
     pub dew_point: f32,
     pub wind_speed: f32,
     pub wind_deg: u64,
+    pub wind_gust: Option<f32>,
     pub weather: Vec<Weather>,
     pub clouds: u64,
     pub pop: f32,
+    /// Rain volume in mm
+    pub rain: Option<f32>,
     pub snow: Option<f32>,
     pub uvi: f32,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
 pub struct WeatherReportOneCallHourly {
-    pub dt: u64,
+    pub dt: Timestamp,
     pub temp: f32,
     pub feels_like: f32,
     pub pressure: u64,
@@ -214,17 +251,29 @@ pub struct WeatherReportOneCallHourly {
     pub visibility: Option<u64>,
     pub wind_speed: f32,
     pub wind_deg: u64,
+    pub wind_gust: Option<f32>,
     pub weather: Vec<Weather>,
+    /// Probability of precipitation, only part of forecasts
+    pub pop: Option<f32>,
+    pub uvi: Option<f32>,
+    pub rain: Option<Rain>,
     pub snow: Option<Snow>,
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct WeatherReportOneCallMinutely {
+    pub dt: Timestamp,
+    /// Precipitation volume in mm/h
+    pub precipitation: f32,
+}
+
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct WeatherAlert {
     pub sender_name: String,
     pub event: String,
     pub description: String,
-    pub start: u64,
-    pub end: u64,
+    pub start: Timestamp,
+    pub end: Timestamp,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
@@ -236,6 +285,91 @@ pub struct WeatherReportOneCall {
     pub current: WeatherReportOneCallCurrent,
     pub daily: Vec<WeatherReportOneCallDaily>,
     pub alerts: Option<Vec<WeatherAlert>>,
+    /// The unit the report was requested in, `Standard` when unknown
+    #[serde(default)]
+    pub unit: Unit,
+}
+
+/// A One Call API 3.0 report, every block not requested being `None`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct WeatherReportOneCall3 {
+    pub lat: f32,
+    pub lon: f32,
+    pub timezone: String,
+    pub timezone_offset: i64,
+    pub current: Option<WeatherReportOneCallCurrent>,
+    pub minutely: Option<Vec<WeatherReportOneCallMinutely>>,
+    pub hourly: Option<Vec<WeatherReportOneCallHourly>>,
+    pub daily: Option<Vec<WeatherReportOneCallDaily>>,
+    pub alerts: Option<Vec<WeatherAlert>>,
+    /// The unit the report was requested in, `Standard` when unknown
+    #[serde(default)]
+    pub unit: Unit,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct DaySummaryTemperature {
+    pub min: f32,
+    pub max: f32,
+    pub morning: f32,
+    pub afternoon: f32,
+    pub evening: f32,
+    pub night: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct DaySummaryAfternoon {
+    pub afternoon: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct DaySummaryPrecipitation {
+    /// Total precipitation in mm
+    pub total: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct DaySummaryWindMax {
+    pub speed: f32,
+    /// Direction in degrees
+    pub direction: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct DaySummaryWind {
+    pub max: DaySummaryWindMax,
+}
+
+/// Weather aggregated over one day by the One Call API 3.0.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct WeatherReportDaySummary {
+    pub lat: f32,
+    pub lon: f32,
+    /// UTC offset the day is aggregated in, as `±HH:MM`
+    pub tz: String,
+    /// Day as `YYYY-MM-DD`
+    pub date: String,
+    pub units: String,
+    /// Cloud cover in %
+    pub cloud_cover: DaySummaryAfternoon,
+    /// Relative humidity in %
+    pub humidity: DaySummaryAfternoon,
+    pub precipitation: DaySummaryPrecipitation,
+    pub temperature: DaySummaryTemperature,
+    /// Atmospheric pressure in hPa
+    pub pressure: DaySummaryAfternoon,
+    pub wind: DaySummaryWind,
+}
+
+/// A human-readable weather summary written by the One Call API 3.0.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct WeatherReportOverview {
+    pub lat: f32,
+    pub lon: f32,
+    pub tz: String,
+    pub date: String,
+    pub units: String,
+    pub weather_overview: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
@@ -245,7 +379,10 @@ pub struct WeatherReportOneCallHistorical {
     pub timezone: String,
     pub timezone_offset: i64,
     pub current: WeatherReportOneCallCurrent,
-    pub hourly: Vec<WeatherReportOneCallHourly>
+    pub hourly: Vec<WeatherReportOneCallHourly>,
+    /// The unit the report was requested in, `Standard` when unknown
+    #[serde(default)]
+    pub unit: Unit,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
@@ -259,13 +396,53 @@ pub struct WeatherReportCurrent {
     pub rain: Option<Rain>,
     pub snow: Option<Snow>,
     pub clouds: Clouds,
-    pub dt: u64,
+    pub dt: Timestamp,
     pub sys: Sys,
     pub timezone: Option<i32>,
     pub id: u64,
     pub name: String,
     pub message: Option<String>,
     pub cod: u16,
+    /// The unit the report was requested in, `Standard` when unknown
+    #[serde(default)]
+    pub unit: Unit,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct CitySys {
+    pub country: Option<String>,
+    pub sunrise: Option<Timestamp>,
+    pub sunset: Option<Timestamp>,
+}
+
+/// The current weather of one city in a `WeatherReportCities`, which carries
+/// fewer details than a `WeatherReportCurrent`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct CityWeather {
+    pub id: u64,
+    pub name: String,
+    pub coord: Coordinates,
+    pub weather: Vec<Weather>,
+    pub main: Main,
+    pub visibility: Option<u32>,
+    pub wind: Wind,
+    pub rain: Option<Rain>,
+    pub snow: Option<Snow>,
+    pub clouds: Clouds,
+    pub dt: Timestamp,
+    pub sys: Option<CitySys>,
+}
+
+/// Current weather for several cities, as returned by `group`, `find` and
+/// `box/city`.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct WeatherReportCities {
+    #[serde(alias = "count")]
+    pub cnt: u32,
+    pub list: Vec<CityWeather>,
+    /// The unit the report was requested in, `Standard` when unknown
+    #[serde(default)]
+    pub unit: Unit,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
@@ -275,6 +452,9 @@ pub struct WeatherReport5Day {
     pub cnt: u8,
     pub list: Vec<TimeSliceHourly>,
     pub city: CityShort,
+    /// The unit the report was requested in, `Standard` when unknown
+    #[serde(default)]
+    pub unit: Unit,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
@@ -284,6 +464,9 @@ pub struct WeatherReport16Day {
     pub city: CityLong,
     pub cnt: u8,
     pub list: Vec<TimeSliceDaily>,
+    /// The unit the report was requested in, `Standard` when unknown
+    #[serde(default)]
+    pub unit: Unit,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
@@ -292,7 +475,7 @@ pub struct WeatherReportHistoricalElement {
     pub wind: Wind,
     pub clouds: Clouds,
     pub weather: Vec<Weather>,
-    pub dt: u64,
+    pub dt: Timestamp,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
@@ -303,6 +486,9 @@ pub struct WeatherReportHistorical {
     pub calctime: f32,
     pub cnt: u32,
     pub list: Vec<WeatherReportHistoricalElement>,
+    /// The unit the report was requested in, `Standard` when unknown
+    #[serde(default)]
+    pub unit: Unit,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
@@ -342,7 +528,7 @@ pub struct UvIndex {
     pub lat: f32,
     pub lon: f32,
     pub data_iso: String,
-    pub date: u64,
+    pub date: Timestamp,
     pub value: f32,
 }
 
@@ -356,7 +542,7 @@ pub struct HistoricalUvIndexElement {
     pub lat: f32,
     pub lon: f32,
     pub date_isp: String,
-    pub date: u64,
+    pub date: Timestamp,
     pub value: u32,
 }
 
@@ -365,6 +551,133 @@ pub struct HistoricalUvIndex {
     pub list: Vec<HistoricalUvIndexElement>,
 }
 
+/// A place found by direct or reverse geocoding.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct GeocodingLocation {
+    pub name: String,
+    /// Name of the place keyed by ISO 639-1 language code, plus the special
+    /// keys `ascii` and `feature_name`
+    #[serde(default)]
+    pub local_names: Option<HashMap<String, String>>,
+    #[serde(flatten)]
+    pub coord: Coordinates,
+    pub country: String,
+    #[serde(default)]
+    pub state: Option<String>,
+}
+
+/// The centroid of a zip or post code.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct GeocodingZip {
+    pub zip: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub coord: Coordinates,
+    pub country: String,
+}
+
+/// Air Quality Index, from 1 (good) to 5 (very poor).
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum AirQualityIndex {
+    #[default]
+    Good = 1,
+    Fair = 2,
+    Moderate = 3,
+    Poor = 4,
+    VeryPoor = 5,
+}
+
+impl TryFrom<u8> for AirQualityIndex {
+    type Error = String;
+
+    fn try_from(aqi: u8) -> Result<Self, Self::Error> {
+        use AirQualityIndex::*;
+        match aqi {
+            1 => Ok(Good),
+            2 => Ok(Fair),
+            3 => Ok(Moderate),
+            4 => Ok(Poor),
+            5 => Ok(VeryPoor),
+            _ => Err(format!("air quality index {} is not between 1 and 5", aqi)),
+        }
+    }
+}
+
+impl From<AirQualityIndex> for u8 {
+    fn from(aqi: AirQualityIndex) -> u8 {
+        aqi as u8
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct AirPollutionMain {
+    pub aqi: AirQualityIndex,
+}
+
+/// Pollutant concentrations in μg/m³
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct AirPollutionComponents {
+    pub co: f32,
+    pub no: f32,
+    pub no2: f32,
+    pub o3: f32,
+    pub so2: f32,
+    pub pm2_5: f32,
+    pub pm10: f32,
+    pub nh3: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct AirPollutionElement {
+    pub dt: Timestamp,
+    pub main: AirPollutionMain,
+    pub components: AirPollutionComponents,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct AirPollution {
+    pub coord: Coordinates,
+    pub list: Vec<AirPollutionElement>,
+}
+
+/// A response of the API, told the `Unit` it was requested in once parsed.
+pub(crate) trait Report {
+    fn set_unit(&mut self, _unit: Unit) {}
+}
+
+macro_rules! reports_with_unit {
+    ($($report:ty),*) => {
+        $(impl Report for $report {
+            fn set_unit(&mut self, unit: Unit) {
+                self.unit = unit;
+            }
+        })*
+    };
+}
+
+reports_with_unit!(
+    WeatherReportCurrent,
+    WeatherReportCities,
+    WeatherReport5Day,
+    WeatherReport16Day,
+    WeatherReportHistorical,
+    WeatherReportOneCall,
+    WeatherReportOneCall3,
+    WeatherReportOneCallHistorical
+);
+
+impl Report for WeatherReportDaySummary {}
+impl Report for WeatherReportOverview {}
+impl Report for WeatherAccumulatedTemperature {}
+impl Report for WeatherAccumulatedPrecipitation {}
+impl Report for UvIndex {}
+impl Report for ForecastUvIndex {}
+impl Report for HistoricalUvIndex {}
+impl Report for AirPollution {}
+impl Report for Vec<GeocodingLocation> {}
+impl Report for GeocodingZip {}
+
 #[cfg(test)]
 mod tests {
     use crate::*;
@@ -375,7 +688,7 @@ mod tests {
         let der_string = "{\"coord\":{\"lon\":10.45,\"lat\":49.57},\"weather\":[{\"id\":801,\"main\":\"Clouds\",\"description\":\"Ein paar Wolken\",\"icon\":\"02d\"}],\"base\":\"stations\",\"main\":{\"temp\":2.94,\"pressure\":998,\"humidity\":86,\"temp_min\":1.67,\"temp_max\":3.89},\"visibility\":10000,\"wind\":{\"speed\":1},\"clouds\":{\"all\":20},\"dt\":1573810268,\"sys\":{\"type\":1,\"id\":1274,\"country\":\"DE\",\"sunrise\":1573799471,\"sunset\":1573832742},\"timezone\":3600,\"id\":2820859,\"name\":\"Berlin\",\"cod\":200}";
 
         let weather_report: WeatherReportCurrent =
-            serde_json::from_str(&der_string).expect("current weather derive failure testcase 1");
+            serde_json::from_str(der_string).expect("current weather derive failure testcase 1");
 
         let weather_report_derived = WeatherReportCurrent {
             coord: Coordinates {
@@ -402,17 +715,14 @@ mod tests {
                 speed: 1.0,
                 ..Default::default()
             },
-            clouds: Clouds {
-                all: 20,
-                ..Default::default()
-            },
-            dt: 1573810268,
+            clouds: Clouds { all: 20 },
+            dt: Timestamp::from_unix(1573810268).unwrap(),
             sys: Sys {
                 message_type: 1,
                 id: 1274,
                 country: "DE".to_string(),
-                sunrise: 1573799471,
-                sunset: 1573832742,
+                sunrise: Timestamp::from_unix(1573799471).unwrap(),
+                sunset: Timestamp::from_unix(1573832742).unwrap(),
                 ..Default::default()
             },
             timezone: Some(3600),
@@ -424,13 +734,7 @@ mod tests {
         };
 
         assert_eq!(weather_report.timezone, Some(3600));
-        assert_eq!(
-            weather_report.clouds,
-            Clouds {
-                all: 20,
-                ..Default::default()
-            }
-        );
+        assert_eq!(weather_report.clouds, Clouds { all: 20 });
 
         assert_eq!(
             weather_report.wind,
@@ -0,0 +1,221 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use std::time::Duration;
+
+use openweather::{
+    AsyncClient, AsyncTransport, BoxFuture, Coordinates, DiskCache, Endpoint, HttpResponse,
+    LocationSpecifier, MockTransport, OneCall, Timestamp, Url,
+};
+use time::OffsetDateTime;
+
+fn client() -> AsyncClient<MockTransport> {
+    AsyncClient::builder("KEY").build_async_with_transport(common::mock())
+}
+
+#[tokio::test]
+async fn current_weather() {
+    let loc = LocationSpecifier::CityId("5037649".to_string());
+    let weather = client().get_current_weather(&loc).await.unwrap();
+    assert_eq!(weather.name, "Minneapolis");
+}
+
+#[tokio::test]
+async fn current_weather_cities() {
+    let loc = LocationSpecifier::Circle {
+        lat: 44.98,
+        lon: -93.26,
+        count: 2,
+    };
+    let weather = client().get_current_weather_cities(&loc).await.unwrap();
+    assert_eq!(weather.list[0].name, "Minneapolis");
+    assert_eq!(
+        weather.list[0].sys.as_ref().unwrap().country.as_deref(),
+        Some("US")
+    );
+}
+
+#[tokio::test]
+async fn forecasts() {
+    let loc = LocationSpecifier::CityId("5037649".to_string());
+    let client = client();
+    assert_eq!(client.get_5_day_forecast(&loc).await.unwrap().cnt, 2);
+    assert_eq!(client.get_16_day_forecast(&loc, 2).await.unwrap().cnt, 2);
+    assert_eq!(
+        client
+            .get_forecast_uv_index(&loc, 2)
+            .await
+            .unwrap()
+            .list
+            .len(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn one_call() {
+    let coordinates = Coordinates {
+        lat: 37.65,
+        lon: -119.04,
+    };
+    let client = client();
+    let current = client.get_one_call_current(&coordinates).await.unwrap();
+    assert_eq!(current.current.uvi, 9.4);
+    let historical = client
+        .get_one_call_historical(&coordinates, Timestamp::from_unix(1590940800).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(historical.timezone, "America/Denver");
+    let report = client
+        .get_one_call(&OneCall::new(&coordinates))
+        .await
+        .unwrap();
+    assert_eq!(report.hourly.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn history() {
+    let loc = LocationSpecifier::CityId("5037649".to_string());
+    let (start, end) = (
+        OffsetDateTime::from_unix_timestamp(1590969600).unwrap(),
+        OffsetDateTime::from_unix_timestamp(1591056000).unwrap(),
+    );
+    let client = client();
+    assert_eq!(
+        client
+            .get_historical_data(&loc, start, end)
+            .await
+            .unwrap()
+            .cnt,
+        2
+    );
+    assert_eq!(
+        client
+            .get_accumulated_temperature_data(&loc, start, end, 284)
+            .await
+            .unwrap()
+            .list
+            .len(),
+        2
+    );
+    assert_eq!(
+        client
+            .get_accumulated_precipitation_data(&loc, start, end, 2)
+            .await
+            .unwrap()
+            .list
+            .len(),
+        2
+    );
+    assert_eq!(
+        client
+            .get_historical_uv_index(&loc, start, end)
+            .await
+            .unwrap()
+            .list
+            .len(),
+        2
+    );
+    assert_eq!(client.get_current_uv_index(&loc).await.unwrap().value, 7.1);
+}
+
+#[tokio::test]
+async fn geocoding() {
+    let client = client();
+    let loc = LocationSpecifier::ZipCode {
+        zip: "55401".to_string(),
+        country: "US".to_string(),
+    };
+    assert_eq!(client.resolve_coordinates(&loc).await.unwrap().lat, 44.9835);
+    let found = client.get_geocoding_direct("Minneapolis", 1).await.unwrap();
+    assert_eq!(found[0].country, "US");
+    let found = client
+        .get_geocoding_reverse(&found[0].coord, 2)
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 2);
+}
+
+#[tokio::test]
+async fn air_pollution() {
+    let coordinates = Coordinates {
+        lat: 44.98,
+        lon: -93.26,
+    };
+    let (start, end) = (
+        OffsetDateTime::from_unix_timestamp(1606435200).unwrap(),
+        OffsetDateTime::from_unix_timestamp(1606442400).unwrap(),
+    );
+    let client = client();
+    assert_eq!(
+        client
+            .get_current_air_pollution(&coordinates)
+            .await
+            .unwrap()
+            .list
+            .len(),
+        1
+    );
+    assert_eq!(
+        client
+            .get_forecast_air_pollution(&coordinates)
+            .await
+            .unwrap()
+            .list
+            .len(),
+        2
+    );
+    assert_eq!(
+        client
+            .get_historical_air_pollution(&coordinates, start, end)
+            .await
+            .unwrap()
+            .list
+            .len(),
+        2
+    );
+}
+
+/// A transport taking its time to answer, for requests to overlap.
+struct Slow(MockTransport);
+
+impl AsyncTransport for Slow {
+    fn get<'a>(&'a self, url: &'a Url) -> BoxFuture<'a, openweather::Result<HttpResponse>> {
+        Box::pin(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            AsyncTransport::get(&self.0, url).await
+        })
+    }
+}
+
+#[tokio::test]
+async fn stale_entries_are_revalidated_once_in_the_background() {
+    let dir = std::env::temp_dir().join(format!(
+        "openweather-async-revalidate-{}",
+        std::process::id()
+    ));
+    let disk_cache = DiskCache::new(&dir)
+        .ttl(Endpoint::Current, Duration::ZERO)
+        .stale_while_revalidate(Duration::from_secs(3600));
+    let loc = LocationSpecifier::CityId("5037649".to_string());
+    let fetched = AsyncClient::builder("KEY")
+        .disk_cache(disk_cache.clone())
+        .build_async_with_transport(common::mock())
+        .get_current_weather(&loc)
+        .await
+        .unwrap();
+
+    let client = AsyncClient::builder("KEY")
+        .disk_cache(disk_cache.clone())
+        .build_async_with_transport(Slow(common::mock()));
+    for _ in 0..3 {
+        assert_eq!(client.get_current_weather(&loc).await.unwrap(), fetched);
+    }
+    assert!(client.transport().0.requests().is_empty());
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(client.transport().0.requests().len(), 1);
+
+    disk_cache.clear().unwrap();
+    std::fs::remove_dir(&dir).unwrap();
+}
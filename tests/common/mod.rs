@@ -0,0 +1,68 @@
+use openweather::MockTransport;
+
+/// A transport answering every endpoint with the fixture of the same name.
+pub fn mock() -> MockTransport {
+    MockTransport::new()
+        .with_json("weather", include_str!("../fixtures/weather.json"))
+        .with_json("group", include_str!("../fixtures/group.json"))
+        .with_json("find", include_str!("../fixtures/find.json"))
+        .with_json("box/city", include_str!("../fixtures/box_city.json"))
+        .with_json("forecast", include_str!("../fixtures/forecast.json"))
+        .with_json(
+            "forecast/daily",
+            include_str!("../fixtures/forecast_daily.json"),
+        )
+        .with_json("onecall", include_str!("../fixtures/onecall.json"))
+        .with_json("3.0/onecall", include_str!("../fixtures/onecall3.json"))
+        .with_json(
+            "3.0/onecall/day_summary",
+            include_str!("../fixtures/day_summary.json"),
+        )
+        .with_json(
+            "3.0/onecall/overview",
+            include_str!("../fixtures/overview.json"),
+        )
+        .with_json(
+            "onecall/timemachine",
+            include_str!("../fixtures/onecall_timemachine.json"),
+        )
+        .with_json(
+            "history/city",
+            include_str!("../fixtures/history_city.json"),
+        )
+        .with_json(
+            "history/accumulated_temperature",
+            include_str!("../fixtures/accumulated_temperature.json"),
+        )
+        .with_json(
+            "history/accumulated_precipitation",
+            include_str!("../fixtures/accumulated_precipitation.json"),
+        )
+        .with_json("uvi", include_str!("../fixtures/uvi.json"))
+        .with_json(
+            "uvi/forecast",
+            include_str!("../fixtures/uvi_forecast.json"),
+        )
+        .with_json("uvi/history", include_str!("../fixtures/uvi_history.json"))
+        .with_json(
+            "air_pollution",
+            include_str!("../fixtures/air_pollution.json"),
+        )
+        .with_json(
+            "air_pollution/forecast",
+            include_str!("../fixtures/air_pollution_forecast.json"),
+        )
+        .with_json(
+            "air_pollution/history",
+            include_str!("../fixtures/air_pollution_history.json"),
+        )
+        .with_json(
+            "geo/1.0/direct",
+            include_str!("../fixtures/geo_direct.json"),
+        )
+        .with_json(
+            "geo/1.0/reverse",
+            include_str!("../fixtures/geo_reverse.json"),
+        )
+        .with_json("geo/1.0/zip", include_str!("../fixtures/geo_zip.json"))
+}
@@ -0,0 +1,742 @@
+#![cfg(feature = "blocking")]
+
+mod common;
+
+use openweather::{
+    AirQualityIndex, Api, Cache, Client, Coordinates, DiskCache, Endpoint, Error, ErrorKind,
+    HttpResponse, KeyPool, KeyStrategy, LocationSpecifier, MockTransport, OneCall, OneCallBlock,
+    RateLimit, RateLimitMode, RetryPolicy, Settings, SpeedUnit, TemperatureScale, Timestamp,
+    Transport, Unit, Url,
+};
+use std::thread;
+use std::time::Duration;
+use time::{Date, Month, OffsetDateTime, UtcOffset};
+
+fn client() -> Client<MockTransport> {
+    Client::builder("KEY").build_with_transport(common::mock())
+}
+
+fn minneapolis() -> LocationSpecifier {
+    LocationSpecifier::CityId("5037649".to_string())
+}
+
+/// A transport taking its time to answer, for requests to overlap.
+struct Slow(MockTransport);
+
+impl Transport for Slow {
+    fn get(&self, url: &Url) -> openweather::Result<HttpResponse> {
+        thread::sleep(Duration::from_millis(100));
+        self.0.get(url)
+    }
+}
+
+fn start_end() -> (OffsetDateTime, OffsetDateTime) {
+    (
+        OffsetDateTime::from_unix_timestamp(1590969600).unwrap(),
+        OffsetDateTime::from_unix_timestamp(1591056000).unwrap(),
+    )
+}
+
+#[test]
+fn current_weather() {
+    let client = client();
+    let weather = client.get_current_weather(&minneapolis()).unwrap();
+    assert_eq!(weather.name, "Minneapolis");
+    assert_eq!(weather.main.temp, 291.48);
+    assert_eq!(weather.sys.sunrise.hour(), 10);
+    assert_eq!(weather.local_sunrise().hour(), 5);
+
+    let url = &client.transport().requests()[0];
+    assert_eq!(url.path(), "/data/2.5/weather");
+    assert_eq!(url.query(), Some("id=5037649&APPID=KEY"));
+}
+
+#[test]
+fn reports_carry_their_unit() {
+    let client = client();
+    let weather = client.get_current_weather(&minneapolis()).unwrap();
+    assert_eq!(weather.unit, Unit::Standard);
+    assert_eq!(
+        weather.measured().main().temperature().kelvin(),
+        291.48f32 as f64
+    );
+
+    let metric = client.with_settings(&Settings {
+        unit: Some(Unit::Metric),
+        lang: None,
+    });
+    let forecast = metric.get_5_day_forecast(&minneapolis()).unwrap();
+    assert_eq!(forecast.unit, Unit::Metric);
+    let slice = forecast.measured().list().next().unwrap();
+    let wind = slice.wind().wind_speed();
+    assert_eq!(wind.unit(), SpeedUnit::MetersPerSecond);
+    assert_eq!(wind.value(), f64::from(forecast.list[0].wind.speed));
+    let temperature = slice.main().temperature();
+    assert_eq!(temperature.scale(), TemperatureScale::Celsius);
+    assert_eq!(temperature.value(), f64::from(forecast.list[0].main.temp));
+
+    let json = serde_json::to_string(&forecast).unwrap();
+    let forecast: openweather::WeatherReport5Day = serde_json::from_str(&json).unwrap();
+    assert_eq!(forecast.unit, Unit::Metric);
+}
+
+#[test]
+fn current_weather_cities() {
+    let client = client();
+    let ids = LocationSpecifier::CityIds(vec!["5037649".to_string(), "5045360".to_string()]);
+    let weather = client.get_current_weather_cities(&ids).unwrap();
+    assert_eq!(weather.cnt, 2);
+    assert_eq!(weather.list[1].name, "Saint Paul");
+
+    let circle = LocationSpecifier::Circle {
+        lat: 44.98,
+        lon: -93.26,
+        count: 2,
+    };
+    let weather = client.get_current_weather_cities(&circle).unwrap();
+    assert_eq!(weather.cnt, 2);
+    assert_eq!(weather.list[1].rain.as_ref().unwrap().one_h, Some(0.25));
+
+    let bbox = LocationSpecifier::BoundingBox {
+        lon_left: -94.0,
+        lat_bottom: 44.0,
+        lon_right: -93.0,
+        lat_top: 45.0,
+        zoom: 10.0,
+    };
+    let weather = client.get_current_weather_cities(&bbox).unwrap();
+    assert_eq!(weather.list[0].coord.lat, 44.98);
+    assert_eq!(weather.list[0].clouds.all, 75);
+
+    let requests = client.transport().requests();
+    assert_eq!(requests[0].path(), "/data/2.5/group");
+    assert_eq!(requests[0].query(), Some("id=5037649%2C5045360&APPID=KEY"));
+    assert_eq!(requests[1].path(), "/data/2.5/find");
+    assert_eq!(requests[2].path(), "/data/2.5/box/city");
+    assert_eq!(
+        requests[2].query(),
+        Some("bbox=-94%2C44%2C-93%2C45%2C10&APPID=KEY")
+    );
+}
+
+#[test]
+fn multi_city_specifiers_are_rejected() {
+    let client = client();
+    let ids = LocationSpecifier::CityIds(vec!["5037649".to_string()]);
+    let err = client.get_current_weather(&ids).unwrap_err();
+    assert!(matches!(err, Error::Input { .. }));
+    let err = client.get_5_day_forecast(&ids).unwrap_err();
+    assert!(matches!(err, Error::Input { .. }));
+
+    let err = client
+        .get_current_weather_cities(&minneapolis())
+        .unwrap_err();
+    assert!(matches!(err, Error::Input { .. }));
+    let too_many = LocationSpecifier::CityIds(vec!["5037649".to_string(); 21]);
+    let err = client.get_current_weather_cities(&too_many).unwrap_err();
+    assert!(matches!(err, Error::Input { .. }));
+    assert!(client.transport().requests().is_empty());
+}
+
+#[test]
+fn forecast_5_day() {
+    let weather = client().get_5_day_forecast(&minneapolis()).unwrap();
+    assert_eq!(weather.list.len(), 2);
+    assert_eq!(weather.list[0].rain.as_ref().unwrap().three_h, Some(0.44));
+}
+
+#[test]
+fn forecast_16_day() {
+    let client = client();
+    let weather = client.get_16_day_forecast(&minneapolis(), 2).unwrap();
+    assert_eq!(weather.list[1].temp.max, 298.4);
+    assert!(client.transport().requests()[0]
+        .query_pairs()
+        .any(|(k, v)| k == "cnt" && v == "2"));
+
+    let err = client.get_16_day_forecast(&minneapolis(), 0).unwrap_err();
+    assert!(matches!(err, Error::Input { .. }));
+    assert_eq!(client.transport().requests().len(), 1);
+}
+
+#[test]
+fn one_call_current() {
+    let coordinates = Coordinates {
+        lat: 37.65,
+        lon: -119.04,
+    };
+    let weather = client()
+        .with_settings(&Settings {
+            unit: Some(Unit::Imperial),
+            lang: None,
+        })
+        .get_one_call_current(&coordinates)
+        .unwrap();
+    assert_eq!(weather.timezone, "America/Los_Angeles");
+    assert_eq!(weather.daily[0].snow, Some(1.3));
+    let feels_like = weather.daily[0].feels_like.as_ref().unwrap();
+    assert_eq!(feels_like.morn, 266.0);
+    let measured = weather.measured();
+    let feels_like = measured.current().feels_like_temperature();
+    assert_eq!(feels_like.scale(), TemperatureScale::Fahrenheit);
+    assert_eq!(feels_like.value(), f64::from(weather.current.feels_like));
+    let day = measured.daily().next().unwrap();
+    assert_eq!(day.wind_speed().unit(), SpeedUnit::MilesPerHour);
+    assert_eq!(day.temperature_max().value(), f64::from(day.temp.max));
+    assert_eq!(
+        weather.alerts.as_ref().unwrap()[0].event,
+        "Winter Storm Warning"
+    );
+
+    let metric = weather.to_unit(Unit::Metric);
+    let feels_like = metric.daily[0].feels_like.as_ref().unwrap();
+    assert!((feels_like.morn - 130.0).abs() < 1e-3);
+}
+
+#[test]
+fn one_call_3() {
+    let coordinates = Coordinates {
+        lat: 44.98,
+        lon: -93.26,
+    };
+    let client = client();
+    let request = OneCall::new(&coordinates).only(&[OneCallBlock::Minutely, OneCallBlock::Hourly]);
+    let weather = client.get_one_call(&request).unwrap();
+    assert_eq!(weather.current, None);
+    assert_eq!(weather.daily, None);
+    assert_eq!(weather.minutely.unwrap()[1].precipitation, 0.21);
+    let hourly = weather.hourly.unwrap();
+    assert_eq!(hourly[0].pop, Some(0.42));
+    assert_eq!(hourly[0].rain.as_ref().unwrap().one_h, Some(0.35));
+
+    let url = &client.transport().requests()[0];
+    assert_eq!(url.path(), "/data/3.0/onecall");
+    assert_eq!(
+        url.query(),
+        Some("lat=44.98&lon=-93.26&exclude=current%2Cdaily%2Calerts&APPID=KEY")
+    );
+}
+
+#[test]
+fn one_call_day_summary_and_overview() {
+    let coordinates = Coordinates {
+        lat: 44.98,
+        lon: -93.26,
+    };
+    let date = Date::from_calendar_date(2020, Month::March, 4).unwrap();
+    let client = client();
+
+    let summary = client
+        .get_one_call_day_summary(&coordinates, date, UtcOffset::from_hms(-5, 0, 0).ok())
+        .unwrap();
+    assert_eq!(summary.temperature.max, 299.24);
+    assert_eq!(summary.wind.max.direction, 120.0);
+    assert_eq!(summary.pressure.afternoon, 1015.0);
+
+    let overview = client.get_one_call_overview(&coordinates, None).unwrap();
+    assert!(overview.weather_overview.starts_with("The current weather"));
+
+    let requests = client.transport().requests();
+    assert_eq!(requests[0].path(), "/data/3.0/onecall/day_summary");
+    assert_eq!(
+        requests[0].query(),
+        Some("lat=44.98&lon=-93.26&date=2020-03-04&tz=-05%3A00&APPID=KEY")
+    );
+    assert_eq!(requests[1].query(), Some("lat=44.98&lon=-93.26&APPID=KEY"));
+}
+
+#[test]
+fn one_call_historical() {
+    let coordinates = Coordinates {
+        lat: 40.46,
+        lon: -106.8,
+    };
+    let client = client();
+    let weather = client
+        .get_one_call_historical(&coordinates, Timestamp::from_unix(1590940800).unwrap())
+        .unwrap();
+    assert_eq!(weather.hourly.len(), 1);
+    assert_eq!(
+        client.transport().requests()[0].path(),
+        "/data/2.5/onecall/timemachine"
+    );
+}
+
+#[test]
+fn historical_data() {
+    let (start, end) = start_end();
+    let weather = client()
+        .get_historical_data(&minneapolis(), start, end)
+        .unwrap();
+    assert_eq!(weather.cnt, 2);
+    assert_eq!(weather.list[1].dt.unix(), 1590973200);
+}
+
+#[test]
+fn accumulated_data() {
+    let (start, end) = start_end();
+    let client = client();
+    let temperature = client
+        .get_accumulated_temperature_data(&minneapolis(), start, end, 284)
+        .unwrap();
+    assert_eq!(temperature.list[1].temp, 1150.2);
+    let precipitation = client
+        .get_accumulated_precipitation_data(&minneapolis(), start, end, 2)
+        .unwrap();
+    assert_eq!(precipitation.list[0].rain, 2.5);
+}
+
+#[test]
+fn uv_index() {
+    let (start, end) = start_end();
+    let client = client();
+    assert_eq!(
+        client.get_current_uv_index(&minneapolis()).unwrap().value,
+        7.1
+    );
+    let forecast = client.get_forecast_uv_index(&minneapolis(), 2).unwrap();
+    assert_eq!(forecast.list.len(), 2);
+    let history = client
+        .get_historical_uv_index(&minneapolis(), start, end)
+        .unwrap();
+    assert_eq!(history.list[1].value, 7);
+}
+
+#[test]
+fn api_error_report() {
+    let mock = MockTransport::new().with_json("weather", include_str!("fixtures/error_401.json"));
+    let client = Client::builder("BAD").build_with_transport(mock);
+    match client.get_current_weather(&minneapolis()) {
+        Err(Error::Api(api)) => assert_eq!(api.report.unwrap().cod, 401),
+        other => panic!("expected an API error, got {:?}", other),
+    }
+}
+
+#[test]
+fn error_statuses_are_classified() {
+    let response = |status: u16, body: &str| HttpResponse {
+        status,
+        headers: vec![],
+        body: body.as_bytes().to_vec(),
+    };
+    let mock = MockTransport::new()
+        .with_response(
+            "weather?id=404",
+            response(404, r#"{"cod":"404","message":"city not found"}"#),
+        )
+        .with_response("weather?id=429", response(429, r#"{"cod":429}"#))
+        .with_response("weather?id=502", response(502, "<html>Bad Gateway</html>"))
+        .with_response("weather?id=200", response(200, "{\"cod\":200"));
+    let client = Client::builder("KEY").build_with_transport(mock);
+    let get = |id: &str| {
+        client
+            .get_current_weather(&LocationSpecifier::CityId(id.to_string()))
+            .unwrap_err()
+    };
+
+    let err = get("404");
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(!err.is_retryable());
+    assert_eq!(
+        err.body(),
+        Some(r#"{"cod":"404","message":"city not found"}"#)
+    );
+
+    assert_eq!(get("429").kind(), ErrorKind::RateLimited);
+    let err = get("502");
+    assert_eq!((err.kind(), err.status()), (ErrorKind::Server, Some(502)));
+    assert!(err.is_retryable());
+    assert_eq!(err.body(), Some("<html>Bad Gateway</html>"));
+
+    let err = get("200");
+    assert_eq!(err.kind(), ErrorKind::Malformed);
+    assert!(matches!(err, Error::MalformedResponse { status: 200, .. }));
+}
+
+#[test]
+fn retryable_errors_are_retried() {
+    let mock = MockTransport::new()
+        .with_response(
+            "weather?id=429",
+            HttpResponse {
+                status: 429,
+                headers: vec![("Retry-After".to_string(), "0".to_string())],
+                body: br#"{"cod":429}"#.to_vec(),
+            },
+        )
+        .with_response(
+            "weather?id=503",
+            HttpResponse {
+                status: 503,
+                ..Default::default()
+            },
+        );
+    let client = Client::builder("KEY")
+        .retry(RetryPolicy::new(3).base_delay(Duration::from_millis(1)))
+        .build_with_transport(mock);
+    let get = |id: &str| {
+        client
+            .get_current_weather(&LocationSpecifier::CityId(id.to_string()))
+            .unwrap_err()
+    };
+
+    let err = get("429");
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(0)));
+    assert_eq!(client.transport().requests().len(), 3);
+    assert_eq!(get("503").status(), Some(503));
+    assert_eq!(client.transport().requests().len(), 6);
+    assert_eq!(get("404").kind(), ErrorKind::NotFound);
+    assert_eq!(client.transport().requests().len(), 7);
+}
+
+#[test]
+fn rate_limit_rejects_calls_over_the_limit() {
+    let client = Client::builder("KEY")
+        .rate_limit(RateLimit::new().per_minute(1).mode(RateLimitMode::Fail))
+        .build_with_transport(common::mock());
+    client.get_current_weather(&minneapolis()).unwrap();
+    let err = client
+        .with_settings(&Settings::default())
+        .get_current_weather(&minneapolis())
+        .unwrap_err();
+    assert!(matches!(err, Error::RateLimited { .. }));
+    assert_eq!(err.kind(), ErrorKind::RateLimited);
+    assert_eq!(client.transport().requests().len(), 1);
+
+    let stats = client.rate_limit_stats().unwrap();
+    assert_eq!((stats.allowed, stats.rejected), (1, 1));
+    assert_eq!(stats.remaining_minute, Some(0));
+}
+
+#[test]
+fn rate_limit_errors_are_not_retried() {
+    let client = Client::builder("KEY")
+        .rate_limit(RateLimit::new().per_day(1).mode(RateLimitMode::Fail))
+        .retry(RetryPolicy::new(3).base_delay(Duration::from_secs(5)))
+        .build_with_transport(common::mock());
+    client.get_current_weather(&minneapolis()).unwrap();
+
+    let start = std::time::Instant::now();
+    let err = client.get_current_weather(&minneapolis()).unwrap_err();
+    assert!(matches!(err, Error::RateLimited { .. }));
+    assert!(!err.is_retryable());
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert_eq!(client.rate_limit_stats().unwrap().rejected, 1);
+    assert_eq!(client.transport().requests().len(), 1);
+}
+
+#[test]
+fn cached_responses_are_reused() {
+    let client = Client::builder("KEY")
+        .cache(Cache::new(10))
+        .build_with_transport(common::mock());
+    let first = client.get_current_weather(&minneapolis()).unwrap();
+    assert_eq!(client.get_current_weather(&minneapolis()).unwrap(), first);
+    assert_eq!(client.transport().requests().len(), 1);
+
+    let metric = client.with_settings(&Settings {
+        unit: Some(Unit::Metric),
+        lang: None,
+    });
+    assert_eq!(
+        metric.get_current_weather(&minneapolis()).unwrap().unit,
+        Unit::Metric
+    );
+    metric.get_5_day_forecast(&minneapolis()).unwrap();
+    assert_eq!(client.transport().requests().len(), 3);
+
+    let stats = client.cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses, stats.entries), (1, 3, 3));
+    client.clear_cache();
+    client.get_current_weather(&minneapolis()).unwrap();
+    assert_eq!(client.transport().requests().len(), 4);
+}
+
+#[test]
+fn rejected_keys_fail_over_and_are_quarantined() {
+    let mock = common::mock().with_response(
+        "weather?APPID=REVOKED",
+        HttpResponse {
+            status: 401,
+            headers: vec![],
+            body: br#"{"cod":401,"message":"Invalid API key"}"#.to_vec(),
+        },
+    );
+    let pool = KeyPool::new(KeyStrategy::Failover)
+        .key("REVOKED")
+        .key("KEY")
+        .key_for(Api::OneCall, "ONE_CALL_KEY");
+    let client = Client::builder("UNUSED")
+        .key_pool(pool)
+        .build_with_transport(mock);
+
+    client.get_current_weather(&minneapolis()).unwrap();
+    client.get_current_weather(&minneapolis()).unwrap();
+    let keys: Vec<_> = client
+        .transport()
+        .requests()
+        .iter()
+        .map(|url| {
+            url.query_pairs()
+                .find(|(name, _)| name == "APPID")
+                .unwrap()
+                .1
+                .into_owned()
+        })
+        .collect();
+    assert_eq!(keys, vec!["REVOKED", "KEY", "KEY"]);
+
+    let stats = client.key_stats();
+    assert_eq!((stats[0].requests, stats[0].rejected), (1, 1));
+    assert!(stats[0].quarantined);
+    assert_eq!(stats[1].requests, 2);
+    assert_eq!((stats[2].api, stats[2].requests), (Some(Api::OneCall), 0));
+}
+
+#[test]
+fn coalesced_requests_use_one_key() {
+    use std::sync::{Arc, Barrier};
+
+    let client = Client::builder("KEY")
+        .coalesce_requests(true)
+        .build_with_transport(Slow(common::mock()));
+    let barrier = Arc::new(Barrier::new(4));
+    let callers: Vec<_> = (0..4)
+        .map(|_| {
+            let client = client.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                client.get_current_weather(&minneapolis()).unwrap()
+            })
+        })
+        .collect();
+    for caller in callers {
+        caller.join().unwrap();
+    }
+    assert_eq!(client.transport().0.requests().len(), 1);
+    assert_eq!(client.key_stats()[0].requests, 1);
+}
+
+#[test]
+fn quarantined_keys_fail_with_their_kind() {
+    let rejected = HttpResponse {
+        status: 401,
+        headers: vec![],
+        body: br#"{"cod":401,"message":"Invalid API key"}"#.to_vec(),
+    };
+    let mock = MockTransport::new().with_response("weather", rejected);
+    let pool = KeyPool::new(KeyStrategy::Failover).key("A").key("B");
+    let client = Client::builder("UNUSED")
+        .key_pool(pool)
+        .build_with_transport(mock);
+
+    let err = client.get_current_weather(&minneapolis()).unwrap_err();
+    assert_eq!(err.status(), Some(401));
+    let err = client.get_current_weather(&minneapolis()).unwrap_err();
+    assert!(matches!(err, Error::KeysUnavailable { rejected: true, .. }));
+    assert_eq!(err.kind(), ErrorKind::InvalidKey);
+    assert_eq!(client.transport().requests().len(), 2);
+}
+
+#[test]
+fn requests_without_a_key_for_their_api_fail_as_input() {
+    let pool = KeyPool::new(KeyStrategy::Failover).key_for(Api::OneCall, "ONE_CALL_KEY");
+    let client = Client::builder("UNUSED")
+        .key_pool(pool)
+        .build_with_transport(common::mock());
+    let err = client.get_current_weather(&minneapolis()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Input);
+    assert!(client.transport().requests().is_empty());
+}
+
+#[test]
+fn disk_cache_outlives_the_client() {
+    let dir = std::env::temp_dir().join(format!("openweather-endpoints-{}", std::process::id()));
+    let disk_cache = DiskCache::new(&dir);
+    let client = |cache: DiskCache, mock: MockTransport| {
+        Client::builder("KEY")
+            .disk_cache(cache)
+            .build_with_transport(mock)
+    };
+    let unavailable = || {
+        MockTransport::new().with_response(
+            "weather",
+            HttpResponse {
+                status: 503,
+                ..Default::default()
+            },
+        )
+    };
+
+    let fetched = client(disk_cache.clone(), common::mock())
+        .get_current_weather(&minneapolis())
+        .unwrap();
+    let offline = client(disk_cache.clone(), unavailable());
+    assert_eq!(
+        offline.get_current_weather(&minneapolis()).unwrap(),
+        fetched
+    );
+    assert!(offline.transport().requests().is_empty());
+
+    let expired = disk_cache.clone().ttl(Endpoint::Current, Duration::ZERO);
+    let stale = client(
+        expired.clone().stale_if_error(Duration::from_secs(3600)),
+        unavailable(),
+    );
+    assert_eq!(stale.get_current_weather(&minneapolis()).unwrap(), fetched);
+    assert_eq!(stale.transport().requests().len(), 1);
+    let err = client(expired, unavailable())
+        .get_current_weather(&minneapolis())
+        .unwrap_err();
+    assert_eq!(err.status(), Some(503));
+
+    disk_cache.clear().unwrap();
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn stale_entries_are_revalidated_once_in_the_background() {
+    let dir = std::env::temp_dir().join(format!("openweather-revalidate-{}", std::process::id()));
+    let disk_cache = DiskCache::new(&dir)
+        .ttl(Endpoint::Current, Duration::ZERO)
+        .stale_while_revalidate(Duration::from_secs(3600));
+    let fetched = Client::builder("KEY")
+        .disk_cache(disk_cache.clone())
+        .build_with_transport(common::mock())
+        .get_current_weather(&minneapolis())
+        .unwrap();
+
+    let client = Client::builder("KEY")
+        .disk_cache(disk_cache.clone())
+        .build_with_transport(Slow(common::mock()));
+    for _ in 0..3 {
+        assert_eq!(client.get_current_weather(&minneapolis()).unwrap(), fetched);
+    }
+    assert!(client.transport().0.requests().is_empty());
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(client.transport().0.requests().len(), 1);
+
+    disk_cache.clear().unwrap();
+    std::fs::remove_dir(&dir).unwrap();
+}
+
+#[test]
+fn geocoding() {
+    let client = client();
+    let found = client.get_geocoding_direct("Minneapolis,MN,US", 1).unwrap();
+    assert_eq!(found[0].state.as_deref(), Some("Minnesota"));
+    assert_eq!(found[0].coord.lat, 44.9773);
+    assert_eq!(found[0].local_names.as_ref().unwrap()["ru"], "Миннеаполис");
+
+    let coordinates = Coordinates {
+        lat: 44.98,
+        lon: -93.26,
+    };
+    let found = client.get_geocoding_reverse(&coordinates, 5).unwrap();
+    assert_eq!(found[1].local_names, None);
+
+    let zip = client.get_geocoding_zip("55401", "US").unwrap();
+    assert_eq!(zip.name, "Minneapolis");
+
+    let url = &client.transport().requests()[0];
+    assert_eq!(url.path(), "/geo/1.0/direct");
+    assert_eq!(
+        url.query(),
+        Some("q=Minneapolis%2CMN%2CUS&limit=1&APPID=KEY")
+    );
+    assert!(matches!(
+        client.get_geocoding_direct("Minneapolis", 6),
+        Err(Error::Input { .. })
+    ));
+}
+
+#[test]
+fn resolve_coordinates() {
+    let client = client();
+    let by_name = LocationSpecifier::CityAndCountryName {
+        city: "Minneapolis".to_string(),
+        country: "US".to_string(),
+    };
+    assert_eq!(client.resolve_coordinates(&by_name).unwrap().lon, -93.2655);
+
+    let by_zip = LocationSpecifier::ZipCode {
+        zip: "55401".to_string(),
+        country: "US".to_string(),
+    };
+    assert_eq!(client.resolve_coordinates(&by_zip).unwrap().lat, 44.9835);
+
+    assert_eq!(
+        client.resolve_coordinates(&minneapolis()).unwrap().lat,
+        44.98
+    );
+
+    let known = LocationSpecifier::Coordinates { lat: 1.0, lon: 2.0 };
+    assert_eq!(
+        client.resolve_coordinates(&known).unwrap(),
+        Coordinates { lat: 1.0, lon: 2.0 }
+    );
+    assert_eq!(client.transport().requests().len(), 3);
+
+    let many = LocationSpecifier::CityIds(vec!["1".to_string(), "2".to_string()]);
+    assert!(matches!(
+        client.resolve_coordinates(&many),
+        Err(Error::Input { .. })
+    ));
+}
+
+#[test]
+fn resolve_coordinates_without_match() {
+    let mock = MockTransport::new().with_json("direct", "[]");
+    let client = Client::builder("KEY").build_with_transport(mock);
+    let nowhere = LocationSpecifier::CityAndCountryName {
+        city: "Nowhere".to_string(),
+        country: "".to_string(),
+    };
+    assert!(matches!(
+        client.resolve_coordinates(&nowhere),
+        Err(Error::Input { .. })
+    ));
+}
+
+#[test]
+fn air_pollution() {
+    let coordinates = Coordinates {
+        lat: 44.98,
+        lon: -93.26,
+    };
+    let (start, end) = (
+        OffsetDateTime::from_unix_timestamp(1606435200).unwrap(),
+        OffsetDateTime::from_unix_timestamp(1606442400).unwrap(),
+    );
+    let client = client();
+
+    let current = client.get_current_air_pollution(&coordinates).unwrap();
+    assert_eq!(current.list[0].main.aqi, AirQualityIndex::Fair);
+    assert_eq!(current.list[0].components.pm2_5, 0.5);
+
+    let forecast = client.get_forecast_air_pollution(&coordinates).unwrap();
+    assert_eq!(forecast.list[1].main.aqi, AirQualityIndex::Poor);
+
+    let history = client
+        .get_historical_air_pollution(&coordinates, start, end)
+        .unwrap();
+    assert_eq!(history.list[1].main.aqi, AirQualityIndex::VeryPoor);
+    assert!(history.list[1].main.aqi > history.list[0].main.aqi);
+
+    let url = &client.transport().requests()[2];
+    assert_eq!(url.path(), "/data/2.5/air_pollution/history");
+    assert_eq!(
+        url.query(),
+        Some("lat=44.98&lon=-93.26&start=1606435200&end=1606442400&APPID=KEY")
+    );
+}
+
+#[test]
+fn air_quality_index_out_of_range() {
+    let body = r#"{"coord":{"lon":0,"lat":0},"list":[{"main":{"aqi":6},"components":{"co":0,"no":0,"no2":0,"o3":0,"so2":0,"pm2_5":0,"pm10":0,"nh3":0},"dt":0}]}"#;
+    let mock = MockTransport::new().with_json("air_pollution", body);
+    let client = Client::builder("KEY").build_with_transport(mock);
+    let coordinates = Coordinates { lat: 0.0, lon: 0.0 };
+    assert!(client.get_current_air_pollution(&coordinates).is_err());
+}
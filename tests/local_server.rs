@@ -0,0 +1,66 @@
+//! Points the default transports at a plain HTTP server on 127.0.0.1.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+
+use openweather::{LocationSpecifier, Url};
+
+/// Serves `body` to a single request and sends back its request line.
+fn serve_once(body: &'static str) -> (Url, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = Url::parse(&format!("http://{}/owm/", listener.local_addr().unwrap())).unwrap();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap() > 2 {
+            line.clear();
+        }
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        tx.send(request_line).unwrap();
+    });
+    (base, rx)
+}
+
+#[cfg(feature = "blocking")]
+#[test]
+fn blocking_client_uses_base_url() {
+    let (base, rx) = serve_once(include_str!("fixtures/uvi.json"));
+    let client = openweather::Client::builder("KEY").base_url(base).build();
+    let loc = LocationSpecifier::CityId("5037649".to_string());
+
+    assert_eq!(client.get_current_uv_index(&loc).unwrap().value, 7.1);
+    assert_eq!(
+        rx.recv().unwrap().trim_end(),
+        "GET /owm/data/2.5/uvi?id=5037649&APPID=KEY HTTP/1.1"
+    );
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn async_client_uses_base_url() {
+    let (base, rx) = serve_once(include_str!("fixtures/uvi.json"));
+    let client = openweather::AsyncClient::builder("KEY")
+        .base_url(base)
+        .api_version("3.0")
+        .build_async()
+        .unwrap();
+    let loc = LocationSpecifier::CityId("5037649".to_string());
+
+    assert_eq!(client.get_current_uv_index(&loc).await.unwrap().value, 7.1);
+    assert_eq!(
+        rx.recv().unwrap().trim_end(),
+        "GET /owm/data/3.0/uvi?id=5037649&APPID=KEY HTTP/1.1"
+    );
+}